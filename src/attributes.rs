@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use crate::color::Color;
+use crate::matrix4::Matrix4;
+use crate::vec2::Vec2;
+use crate::vec4::Vec4;
+
+/// Data stored per vertex next to its position. Every channel is either empty, when the mesh doesn't
+/// have it, or has one value for every vertex.
+#[derive(Clone, Default)]
+pub struct VertexAttributes {
+    pub normals: Vec<Vec4>,
+    pub uvs: Vec<Vec2>,
+    pub colors: Vec<Color>,
+    pub tangents: Vec<Vec4>, // along the direction u grows in
+    pub scalars: BTreeMap<String, Vec<f32>>, // any numbers per vertex, like a temperature or a stress
+}
+
+impl VertexAttributes {
+    /// The attributes of new vertices that are copies of old ones, new vertex i copying `sources[i]`
+    pub fn select(&self, sources: &[usize]) -> Self {
+        fn pick<T: Copy>(channel: &[T], sources: &[usize]) -> Vec<T> {
+            if channel.is_empty() { Vec::new() } else { sources.iter().map(|&i| channel[i]).collect() }
+        }
+        Self{
+            normals: pick(&self.normals, sources),
+            uvs: pick(&self.uvs, sources),
+            colors: pick(&self.colors, sources),
+            tangents: pick(&self.tangents, sources),
+            scalars: self.scalars.iter().map(|(name, values)| (name.clone(), pick(values, sources))).collect(),
+        }
+    }
+
+    /// The attributes of new vertices that lie between old ones, new vertex i being the weighted sum of
+    /// the old vertices in `sources[i]`. The weights of every new vertex should add up to 1. Normals and
+    /// tangents are normalized again afterwards.
+    pub fn blend(&self, sources: &[Vec<(usize, f32)>]) -> Self {
+        fn mix<T: Copy, S>(channel: &[T], sources: &[Vec<(usize, f32)>], zero: S, add: impl Fn(S, T, f32) -> S) -> Vec<S>
+            where S: Copy {
+            if channel.is_empty() { return Vec::new(); }
+            sources.iter()
+                .map(|weights| weights.iter().fold(zero, |acc, &(i, weight)| add(acc, channel[i], weight)))
+                .collect()
+        }
+        let add_vec4 = |acc: Vec4, v: Vec4, weight: f32| acc.add(&v.scale(weight));
+        let direction = |v: Vec4| if v.length() > 0.0 { v.normalize() } else { v };
+        let zero = Vec4::new_vec4(0.0, 0.0, 0.0);
+        Self{
+            normals: mix(&self.normals, sources, zero, add_vec4).into_iter().map(direction).collect(),
+            uvs: mix(&self.uvs, sources, Vec2::new(0.0, 0.0), |acc, uv, weight| Vec2::new(acc.x() + uv.x() * weight, acc.y() + uv.y() * weight)),
+            colors: mix(&self.colors, sources, [0.0; 3], |acc, c, weight| [acc[0] + c.r() * weight, acc[1] + c.g() * weight, acc[2] + c.b() * weight])
+                .into_iter().map(|[r, g, b]| Color::new(r, g, b)).collect(),
+            tangents: mix(&self.tangents, sources, zero, add_vec4).into_iter().map(direction).collect(),
+            scalars: self.scalars.iter()
+                .map(|(name, values)| (name.clone(), mix(values, sources, 0.0, |acc, value, weight| acc + value * weight)))
+                .collect(),
+        }
+    }
+
+    /// The attributes of both, one after the other. Channels only one of them has are dropped.
+    pub fn concat(&self, other: &Self) -> Self {
+        fn join<T: Copy>(a: &[T], b: &[T]) -> Vec<T> {
+            if a.is_empty() || b.is_empty() { Vec::new() } else { a.iter().chain(b.iter()).copied().collect() }
+        }
+        Self{
+            normals: join(&self.normals, &other.normals),
+            uvs: join(&self.uvs, &other.uvs),
+            colors: join(&self.colors, &other.colors),
+            tangents: join(&self.tangents, &other.tangents),
+            scalars: self.scalars.iter()
+                .filter_map(|(name, values)| other.scalars.get(name).map(|others| (name.clone(), join(values, others))))
+                .collect(),
+        }
+    }
+
+    /// Turns the normals and tangents along with the vertices
+    pub fn transform(&mut self, t: &Matrix4) {
+        for direction in self.normals.iter_mut().chain(self.tangents.iter_mut()) {
+            *direction = direction.mul(t).normalize();
+        }
+    }
+}
+
+#[test]
+fn test_vertex_attributes() {
+    let mut attributes = VertexAttributes{
+        uvs: vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)],
+        colors: vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 1.0)],
+        ..Default::default()
+    };
+    attributes.scalars.insert("heat".to_string(), vec![10.0, 20.0, 30.0]);
+
+    let selected = attributes.select(&[2, 2, 0]);
+    assert_eq!(selected.uvs, vec![Vec2::new(0.0, 1.0), Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0)]);
+    assert!(selected.normals.is_empty());
+
+    let blended = attributes.blend(&[vec![(0, 0.5), (1, 0.5)], vec![(0, 0.25), (1, 0.25), (2, 0.5)]]);
+    assert_eq!(blended.uvs, vec![Vec2::new(0.5, 0.0), Vec2::new(0.25, 0.5)]);
+    assert!((blended.colors[0].r() - 0.5).abs() < 1e-6 && (blended.colors[0].g() - 0.5).abs() < 1e-6);
+    assert_eq!(blended.scalars["heat"], vec![15.0, 22.5]);
+
+    let without_colors = VertexAttributes{colors: Vec::new(), ..attributes.clone()};
+    let joined = attributes.concat(&without_colors);
+    assert_eq!(joined.uvs.len(), 6);
+    assert!(joined.colors.is_empty());
+    assert_eq!(joined.scalars["heat"].len(), 6);
+}
@@ -0,0 +1,99 @@
+use crate::matrix4::Matrix4;
+use crate::vec4::Vec4;
+
+/// An axis aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec4,
+    pub max: Vec4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec4,
+    pub radius: f32,
+}
+
+impl Aabb {
+    /// None without any points
+    pub fn from_points(points: &[Vec4]) -> Option<Self> {
+        let first = points.first()?;
+        let (mut min, mut max) = ([first.x(), first.y(), first.z()], [first.x(), first.y(), first.z()]);
+        for p in points.iter() {
+            for (axis, c) in [p.x(), p.y(), p.z()].into_iter().enumerate() {
+                min[axis] = min[axis].min(c);
+                max[axis] = max[axis].max(c);
+            }
+        }
+        Some(Self{min: Vec4::new_point(min[0], min[1], min[2]), max: Vec4::new_point(max[0], max[1], max[2])})
+    }
+
+    pub fn center(&self) -> Vec4 {
+        self.min.add(&self.size().scale(0.5))
+    }
+
+    /// The vector from the smallest to the largest corner
+    pub fn size(&self) -> Vec4 {
+        self.max.sub(&self.min)
+    }
+
+    /// The smallest box around both boxes
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_points(&[self.min, self.max, other.min, other.max]).unwrap()
+    }
+}
+
+impl BoundingSphere {
+    /// Ritter's sphere: starts from the two points furthest apart along the way from any point and grows
+    /// to take in the points outside of it. At most about 5% larger than the smallest sphere.
+    pub fn from_points(points: &[Vec4]) -> Option<Self> {
+        let furthest_from = |p: &Vec4| *points.iter()
+            .max_by(|a, b| a.sub(p).length().total_cmp(&b.sub(p).length()))
+            .unwrap();
+        let a = furthest_from(points.first()?);
+        let b = furthest_from(&a);
+
+        let mut center = a.add(&b.sub(&a).scale(0.5));
+        let mut radius = b.sub(&a).length() / 2.0;
+        for p in points.iter() {
+            let distance = p.sub(&center).length();
+            if distance > radius {
+                // the new sphere touches p and the far side of the old one
+                let new_radius = (radius + distance) / 2.0;
+                center = center.add(&p.sub(&center).scale((new_radius - radius) / distance));
+                radius = new_radius;
+            }
+        }
+        Some(Self{center, radius})
+    }
+
+    /// The radius grows with the axis the transformation stretches the most
+    pub fn transform(&self, t: &Matrix4) -> Self {
+        let stretch = (0..3)
+            .map(|row| Vec4::new_vec4(t.elements[row][0], t.elements[row][1], t.elements[row][2]).length())
+            .fold(0.0, f32::max);
+        Self{center: self.center.mul(t), radius: self.radius * stretch}
+    }
+}
+
+#[test]
+fn test_bounds() {
+    use std::f32::consts::PI;
+
+    let points = [Vec4::new_point(-1.0, 0.0, 2.0), Vec4::new_point(3.0, -2.0, 0.0), Vec4::new_point(1.0, 4.0, 1.0)];
+    let aabb = Aabb::from_points(&points).unwrap();
+    assert_eq!(aabb.min, Vec4::new_point(-1.0, -2.0, 0.0));
+    assert_eq!(aabb.max, Vec4::new_point(3.0, 4.0, 2.0));
+    assert_eq!(aabb.center(), Vec4::new_point(1.0, 1.0, 1.0));
+    assert!(Aabb::from_points(&[]).is_none());
+
+    let sphere = BoundingSphere::from_points(&points).unwrap();
+    assert!(points.iter().all(|p| sphere.radius - p.sub(&sphere.center).length() > -1e-5));
+
+    // turned a quarter around z and moved, a sphere stays the same size
+    let t = Matrix4::mul(&Matrix4::new_rotation_z(PI / 2.0), &Matrix4::new_translation(&Vec4::new_vec4(10.0, 0.0, 0.0)));
+    let moved = sphere.transform(&t);
+    assert!((moved.radius - sphere.radius).abs() < 1e-5);
+    assert!(points.iter().all(|p| moved.radius - p.mul(&t).sub(&moved.center).length() > -1e-4));
+    assert!((sphere.transform(&Matrix4::new_scaling(2.0)).radius - 2.0 * sphere.radius).abs() < 1e-5);
+}
@@ -18,9 +18,9 @@ impl Color {
 
     pub fn to_pixel(&self) -> bmp::Pixel {
         px!(
-                self.r() as u8 *255,
-                self.g() as u8 *255,
-                self.b() as u8 *255
+                (self.r() * 255.0).round() as u8,
+                (self.g() * 255.0).round() as u8,
+                (self.b() * 255.0).round() as u8
             )
     }
 }
\ No newline at end of file
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use crate::figure::{Face, Mesh};
+use crate::vec4::Vec4;
+use crate::point3::{self, Point, add, sub, scale, dot, length, cross};
+
+struct HullFace {
+    indexes: [usize; 3], // counter clock wise seen from outside the hull
+    normal: Point,
+    offset: f64, // normal . p == offset for every point p in the plane of the face
+    outside: Vec<usize>, // points in front of this face, not yet on the hull
+    alive: bool,
+}
+
+/// Builds the convex hull of the points with quickhull. Adjacent triangles in the same plane are
+/// merged into one polygon. Points that lie inside the hull or on one of its faces are left out.
+/// Returns a mesh without faces when the points are all in one plane.
+pub fn quickhull(points: &[Vec4]) -> Mesh {
+    let points: Vec<Point> = points.iter().map(point3::from_vec4).collect();
+
+    let extent = points.iter()
+        .flat_map(|p| p.iter())
+        .fold(0.0_f64, |max, c| max.max(c.abs()));
+    let eps = extent.max(1.0) * 1e-9 * 3.0 * points.len().max(1) as f64;
+
+    let Some(initial) = initial_tetrahedron(&points, eps) else {
+        eprintln!("convex hull: the points are all in one plane, the hull has no volume");
+        return Mesh::new(Vec::new(), Vec::new());
+    };
+
+    let mut faces: Vec<HullFace> = Vec::new();
+    // face on the left of every directed edge
+    let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+
+    let [a, b, c, d] = initial;
+    let center = scale(&add(&add(&points[a], &points[b]), &add(&points[c], &points[d])), 0.25);
+    for [i, j, k] in [[a, b, c], [a, b, d], [a, c, d], [b, c, d]] {
+        let mut face = new_face(&points, [i, j, k]);
+        if distance(&face, &center) > 0.0 {
+            face = new_face(&points, [i, k, j]);
+        }
+        add_face(&mut faces, &mut edge_faces, face);
+    }
+
+    // every remaining point goes to the first face it is in front of
+    for (point_i, point) in points.iter().enumerate() {
+        if initial.contains(&point_i) { continue; }
+        if let Some(face) = faces.iter_mut().find(|face| distance(face, point) > eps) {
+            face.outside.push(point_i);
+        }
+    }
+
+    while let Some(face_i) = faces.iter().position(|face| face.alive && !face.outside.is_empty()) {
+        // the point furthest in front of the face is certainly on the hull
+        let eye = *faces[face_i].outside.iter()
+            .max_by(|&&p, &&q| distance(&faces[face_i], &points[p]).total_cmp(&distance(&faces[face_i], &points[q])))
+            .unwrap();
+
+        // flood the faces the eye can see, their border with the other faces is the horizon
+        let mut visible = vec![face_i];
+        let mut stack = vec![face_i];
+        let mut horizon = Vec::new();
+        while let Some(current) = stack.pop() {
+            let indexes = faces[current].indexes;
+            for edge_i in 0..3 {
+                let (from, to) = (indexes[edge_i], indexes[(edge_i+1)%3]);
+                let neighbour = edge_faces[&(to, from)];
+                if visible.contains(&neighbour) { continue; }
+                if distance(&faces[neighbour], &points[eye]) > eps {
+                    visible.push(neighbour);
+                    stack.push(neighbour);
+                }
+                else {
+                    horizon.push((from, to));
+                }
+            }
+        }
+
+        let mut orphans = Vec::new();
+        for &visible_i in visible.iter() {
+            let face = &mut faces[visible_i];
+            face.alive = false;
+            orphans.append(&mut face.outside);
+            let indexes = face.indexes;
+            for edge_i in 0..3 {
+                edge_faces.remove(&(indexes[edge_i], indexes[(edge_i+1)%3]));
+            }
+        }
+
+        let first_new_face = faces.len();
+        for (from, to) in horizon {
+            add_face(&mut faces, &mut edge_faces, new_face(&points, [from, to, eye]));
+        }
+
+        for point_i in orphans {
+            if point_i == eye { continue; }
+            let new_faces = &mut faces[first_new_face..];
+            if let Some(face) = new_faces.iter_mut().find(|face| distance(face, &points[point_i]) > eps) {
+                face.outside.push(point_i);
+            }
+        }
+    }
+
+    let alive: Vec<&HullFace> = faces.iter().filter(|face| face.alive).collect();
+    let tolerance = extent.max(1.0) * 1e-6;
+    let polygons: Vec<Vec<usize>> = merge_coplanar(&alive, tolerance).into_iter()
+        .map(|polygon| drop_collinear(&points, polygon, tolerance))
+        .collect();
+
+    // keep only the points on the hull
+    let mut new_indexes = HashMap::new();
+    let mut vertices = Vec::new();
+    let faces = polygons.into_iter().map(|polygon| {
+        Face::new(polygon.into_iter().map(|i| {
+            *new_indexes.entry(i).or_insert_with(|| {
+                let p = points[i];
+                vertices.push(Vec4::new_point(p[0] as f32, p[1] as f32, p[2] as f32));
+                vertices.len() - 1
+            })
+        }).collect())
+    }).collect();
+
+    Mesh::new(vertices, faces)
+}
+
+fn initial_tetrahedron(points: &[Point], eps: f64) -> Option<[usize; 4]> {
+    if points.len() < 4 { return None; }
+
+    // the two points furthest apart among the extremes along every axis
+    let mut extremes = Vec::new();
+    for axis in 0..3 {
+        let by_axis = |&i: &usize, &j: &usize| points[i][axis].total_cmp(&points[j][axis]);
+        extremes.push((0..points.len()).min_by(by_axis).unwrap());
+        extremes.push((0..points.len()).max_by(by_axis).unwrap());
+    }
+    let mut best = (0.0, 0, 0);
+    for &i in extremes.iter() {
+        for &j in extremes.iter() {
+            let d = length(&sub(&points[i], &points[j]));
+            if d > best.0 { best = (d, i, j); }
+        }
+    }
+    let (line_length, a, b) = best;
+    if line_length <= eps { return None; }
+
+    let line = sub(&points[b], &points[a]);
+    let c = (0..points.len())
+        .max_by(|&i, &j| {
+            let distance_to_line = |p: usize| length(&cross(&line, &sub(&points[p], &points[a])));
+            distance_to_line(i).total_cmp(&distance_to_line(j))
+        })
+        .unwrap();
+    let normal = cross(&line, &sub(&points[c], &points[a]));
+    if length(&normal) <= eps * line_length { return None; }
+
+    let d = (0..points.len())
+        .max_by(|&i, &j| {
+            let distance_to_plane = |p: usize| dot(&normal, &sub(&points[p], &points[a])).abs();
+            distance_to_plane(i).total_cmp(&distance_to_plane(j))
+        })
+        .unwrap();
+    if dot(&normal, &sub(&points[d], &points[a])).abs() / length(&normal) <= eps { return None; }
+
+    Some([a, b, c, d])
+}
+
+fn new_face(points: &[Point], indexes: [usize; 3]) -> HullFace {
+    let [a, b, c] = indexes.map(|i| points[i]);
+    let normal = cross(&sub(&b, &a), &sub(&c, &a));
+    let normal = scale(&normal, 1.0 / length(&normal));
+    HullFace{indexes, normal, offset: dot(&normal, &a), outside: Vec::new(), alive: true}
+}
+
+fn add_face(faces: &mut Vec<HullFace>, edge_faces: &mut HashMap<(usize, usize), usize>, face: HullFace) {
+    for edge_i in 0..3 {
+        edge_faces.insert((face.indexes[edge_i], face.indexes[(edge_i+1)%3]), faces.len());
+    }
+    faces.push(face);
+}
+
+fn distance(face: &HullFace, p: &Point) -> f64 {
+    dot(&face.normal, p) - face.offset
+}
+
+/// Groups neighbouring triangles lying in the same plane, up to `tolerance`, and returns the outline of every group
+fn merge_coplanar(faces: &[&HullFace], tolerance: f64) -> Vec<Vec<usize>> {
+    let mut edge_faces = HashMap::new();
+    for (face_i, face) in faces.iter().enumerate() {
+        for edge_i in 0..3 {
+            edge_faces.insert((face.indexes[edge_i], face.indexes[(edge_i+1)%3]), face_i);
+        }
+    }
+
+    let coplanar = |f: &HullFace, g: &HullFace| {
+        dot(&f.normal, &g.normal) > 1.0 - 1e-6 && (g.offset - f.offset).abs() <= tolerance
+    };
+
+    let mut group_of = vec![usize::MAX; faces.len()];
+    let mut polygons = Vec::new();
+
+    for start in 0..faces.len() {
+        if group_of[start] != usize::MAX { continue; }
+
+        let mut group = vec![start];
+        group_of[start] = start;
+        let mut stack = vec![start];
+        while let Some(current) = stack.pop() {
+            let indexes = faces[current].indexes;
+            for edge_i in 0..3 {
+                let neighbour = edge_faces[&(indexes[(edge_i+1)%3], indexes[edge_i])];
+                if group_of[neighbour] == usize::MAX && coplanar(faces[start], faces[neighbour]) {
+                    group_of[neighbour] = start;
+                    group.push(neighbour);
+                    stack.push(neighbour);
+                }
+            }
+        }
+
+        // the outline consists of the edges whose other side is not in the group
+        let mut next_on_outline = HashMap::new();
+        for &face_i in group.iter() {
+            let indexes = faces[face_i].indexes;
+            for edge_i in 0..3 {
+                let (from, to) = (indexes[edge_i], indexes[(edge_i+1)%3]);
+                if group_of[edge_faces[&(to, from)]] != start {
+                    next_on_outline.insert(from, to);
+                }
+            }
+        }
+
+        let first = *next_on_outline.keys().min().unwrap();
+        let mut polygon = vec![first];
+        let mut current = next_on_outline[&first];
+        while current != first {
+            polygon.push(current);
+            current = next_on_outline[&current];
+        }
+        polygons.push(polygon);
+    }
+
+    polygons
+}
+
+/// Leaves out the corners that lie on a straight edge of the polygon, such a point is on an edge
+/// of the hull, so it is left out of the polygon on the other side of that edge as well
+fn drop_collinear(points: &[Point], polygon: Vec<usize>, tolerance: f64) -> Vec<usize> {
+    let n = polygon.len();
+    (0..n)
+        .filter(|&i| {
+            let (previous, current, next) = (points[polygon[(i+n-1)%n]], points[polygon[i]], points[polygon[(i+1)%n]]);
+            let edge = sub(&next, &previous);
+            length(&cross(&edge, &sub(&current, &previous))) > tolerance * length(&edge)
+        })
+        .map(|i| polygon[i])
+        .collect()
+}
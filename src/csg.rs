@@ -0,0 +1,318 @@
+use crate::attributes::VertexAttributes;
+use crate::figure::{Face, Mesh};
+use crate::vec4::Vec4;
+use crate::point3::{self, Point, dot, length};
+
+/// Which vertices of the two meshes a point lies between, as weights of their attributes. The vertices
+/// of the second mesh are numbered after those of the first.
+type Sources = Vec<(usize, f32)>;
+
+/// How far a point may lie from a plane and still count as lying in it
+const EPSILON: f64 = 1e-5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference, // the first operand minus the second
+}
+
+#[derive(Clone, Copy)]
+struct Plane {
+    normal: Point,
+    offset: f64, // normal . p == offset for every point p in the plane
+}
+
+#[derive(Clone)]
+struct Polygon {
+    points: Vec<Point>,
+    sources: Vec<Sources>, // one for every point
+    plane: Plane,
+}
+
+/// A node splits space by the plane of its first polygon, the polygons in that plane are kept in the
+/// node itself and the others go to the subtree in front of or behind it. Nodes are stored in a list
+/// and point to each other by index, so deep trees don't need deep recursion.
+struct Node {
+    plane: Plane,
+    front: Option<usize>,
+    back: Option<usize>,
+    polygons: Vec<Polygon>,
+}
+
+/// A binary space partitioning tree of the faces of a closed mesh, the inside of the mesh
+/// is behind all of its faces
+struct Bsp {
+    nodes: Vec<Node>,
+}
+
+/// Combines two closed meshes with binary space partitioning trees, as in csg.js by Evan Wallace:
+/// every mesh clips away the faces of the other one that lie inside of it (or outside, for an
+/// intersection) and the remaining faces of both are joined. Faces are split where they cross,
+/// the pieces are welded back together at their shared corners. Vertex attributes both meshes have
+/// are blended along where faces are split.
+pub fn csg(a: &Mesh, b: &Mesh, operation: CsgOperation) -> Mesh {
+    let attributes = a.attributes.concat(&b.attributes);
+    let (a, b) = (to_polygons(a, 0), to_polygons(b, a.vertices.len()));
+    let mut a = Bsp::new(a);
+    let mut b = Bsp::new(b);
+
+    match operation {
+        CsgOperation::Union => {
+            a.clip_to(&b);
+            b.clip_to(&a);
+            b.invert();
+            b.clip_to(&a);
+            b.invert();
+            a.build(b.all_polygons());
+        }
+        CsgOperation::Difference => {
+            a.invert();
+            a.clip_to(&b);
+            b.clip_to(&a);
+            b.invert();
+            b.clip_to(&a);
+            b.invert();
+            a.build(b.all_polygons());
+            a.invert();
+        }
+        CsgOperation::Intersection => {
+            a.invert();
+            b.clip_to(&a);
+            b.invert();
+            a.clip_to(&b);
+            b.clip_to(&a);
+            a.build(b.all_polygons());
+            a.invert();
+        }
+    }
+
+    to_mesh(a.all_polygons(), &attributes)
+}
+
+impl Plane {
+    /// The plane through the polygon by Newell's method, None if it has no area
+    fn through(points: &[Point]) -> Option<Self> {
+        let mut normal = [0.0; 3];
+        for (i, p) in points.iter().enumerate() {
+            let q = points[(i+1) % points.len()];
+            normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+            normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+            normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+        }
+        let normal_length = length(&normal);
+        if normal_length <= EPSILON * EPSILON { return None; }
+        let normal = normal.map(|c| c / normal_length);
+        Some(Self{normal, offset: dot(&normal, &points[0])})
+    }
+
+    fn flip(&mut self) {
+        self.normal = self.normal.map(|c| -c);
+        self.offset = -self.offset;
+    }
+
+    /// Sorts the polygon into the lists of polygons in front of or behind the plane, splitting it
+    /// when it lies on both sides. Polygons in the plane go to the front or back depending on which
+    /// way they face.
+    fn split(&self, polygon: Polygon, coplanar_front: &mut Vec<Polygon>, coplanar_back: &mut Vec<Polygon>,
+             front: &mut Vec<Polygon>, back: &mut Vec<Polygon>) {
+        const COPLANAR: u8 = 0;
+        const FRONT: u8 = 1;
+        const BACK: u8 = 2;
+        const SPANNING: u8 = 3;
+
+        let distances: Vec<f64> = polygon.points.iter().map(|p| dot(&self.normal, p) - self.offset).collect();
+        let sides: Vec<u8> = distances.iter()
+            .map(|&d| if d < -EPSILON { BACK } else if d > EPSILON { FRONT } else { COPLANAR })
+            .collect();
+        let polygon_side = sides.iter().fold(COPLANAR, |acc, side| acc | side);
+
+        match polygon_side {
+            COPLANAR => {
+                if dot(&self.normal, &polygon.plane.normal) > 0.0 { coplanar_front.push(polygon) } else { coplanar_back.push(polygon) }
+            }
+            FRONT => front.push(polygon),
+            BACK => back.push(polygon),
+            _ => {
+                let (mut front_points, mut back_points) = (Vec::new(), Vec::new());
+                let (mut front_sources, mut back_sources) = (Vec::new(), Vec::new());
+                let n = polygon.points.len();
+                for i in 0..n {
+                    let j = (i+1) % n;
+                    let (p, q) = (polygon.points[i], polygon.points[j]);
+                    if sides[i] != BACK {
+                        front_points.push(p);
+                        front_sources.push(polygon.sources[i].clone());
+                    }
+                    if sides[i] != FRONT {
+                        back_points.push(p);
+                        back_sources.push(polygon.sources[i].clone());
+                    }
+                    if sides[i] | sides[j] == SPANNING {
+                        let t = distances[i] / (distances[i] - distances[j]);
+                        let crossing = [p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t, p[2] + (q[2] - p[2]) * t];
+                        let sources = mix(&polygon.sources[i], &polygon.sources[j], t as f32);
+                        front_points.push(crossing);
+                        back_points.push(crossing);
+                        front_sources.push(sources.clone());
+                        back_sources.push(sources);
+                    }
+                }
+                if front_points.len() >= 3 { front.push(Polygon{points: front_points, sources: front_sources, plane: polygon.plane}); }
+                if back_points.len() >= 3 { back.push(Polygon{points: back_points, sources: back_sources, plane: polygon.plane}); }
+            }
+        }
+    }
+}
+
+impl Bsp {
+    fn new(polygons: Vec<Polygon>) -> Self {
+        let mut bsp = Self{nodes: Vec::new()};
+        bsp.build(polygons);
+        bsp
+    }
+
+    fn new_node(&mut self, plane: Plane) -> usize {
+        self.nodes.push(Node{plane, front: None, back: None, polygons: Vec::new()});
+        self.nodes.len() - 1
+    }
+
+    /// Adds the polygons to the tree, growing new nodes where they end up outside of it
+    fn build(&mut self, polygons: Vec<Polygon>) {
+        if polygons.is_empty() { return; }
+        if self.nodes.is_empty() {
+            self.new_node(polygons[0].plane);
+        }
+
+        let mut work = vec![(0, polygons)];
+        while let Some((node_i, polygons)) = work.pop() {
+            let plane = self.nodes[node_i].plane;
+            let (mut coplanar, mut front, mut back) = (Vec::new(), Vec::new(), Vec::new());
+            let mut coplanar_back = Vec::new();
+            for polygon in polygons {
+                plane.split(polygon, &mut coplanar, &mut coplanar_back, &mut front, &mut back);
+            }
+            coplanar.append(&mut coplanar_back);
+            self.nodes[node_i].polygons.append(&mut coplanar);
+
+            if !front.is_empty() {
+                let child = match self.nodes[node_i].front {
+                    Some(child) => child,
+                    None => {
+                        let child = self.new_node(front[0].plane);
+                        self.nodes[node_i].front = Some(child);
+                        child
+                    }
+                };
+                work.push((child, front));
+            }
+            if !back.is_empty() {
+                let child = match self.nodes[node_i].back {
+                    Some(child) => child,
+                    None => {
+                        let child = self.new_node(back[0].plane);
+                        self.nodes[node_i].back = Some(child);
+                        child
+                    }
+                };
+                work.push((child, back));
+            }
+        }
+    }
+
+    /// Turns the solid inside out
+    fn invert(&mut self) {
+        for node in self.nodes.iter_mut() {
+            for polygon in node.polygons.iter_mut() {
+                polygon.points.reverse();
+                polygon.sources.reverse();
+                polygon.plane.flip();
+            }
+            node.plane.flip();
+            std::mem::swap(&mut node.front, &mut node.back);
+        }
+    }
+
+    /// The parts of the polygons outside of the solid
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        if self.nodes.is_empty() { return polygons; }
+
+        let mut outside = Vec::new();
+        let mut work = vec![(0, polygons)];
+        while let Some((node_i, polygons)) = work.pop() {
+            let node = &self.nodes[node_i];
+            let (mut front, mut back) = (Vec::new(), Vec::new());
+            let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+            for polygon in polygons {
+                node.plane.split(polygon, &mut coplanar_front, &mut coplanar_back, &mut front, &mut back);
+            }
+            front.append(&mut coplanar_front);
+            back.append(&mut coplanar_back);
+
+            match node.front {
+                Some(child) => work.push((child, front)),
+                None => outside.append(&mut front),
+            }
+            // behind a leaf is inside the solid
+            if let Some(child) = node.back {
+                work.push((child, back));
+            }
+        }
+        outside
+    }
+
+    /// Removes the parts of this tree's polygons that lie inside the other solid
+    fn clip_to(&mut self, other: &Bsp) {
+        for node in self.nodes.iter_mut() {
+            let polygons = std::mem::take(&mut node.polygons);
+            node.polygons = other.clip_polygons(polygons);
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        self.nodes.iter().flat_map(|node| node.polygons.iter().cloned()).collect()
+    }
+}
+
+/// The faces as polygons, their vertices numbered from `first_index` on
+fn to_polygons(mesh: &Mesh, first_index: usize) -> Vec<Polygon> {
+    mesh.faces.iter()
+        .filter_map(|face| {
+            let points: Vec<Point> = face.indexes.iter()
+                .map(|&i| point3::from_vec4(&mesh.vertices[i]))
+                .collect();
+            let sources = face.indexes.iter().map(|&i| vec![(first_index + i, 1.0)]).collect();
+            Plane::through(&points).map(|plane| Polygon{points, sources, plane})
+        })
+        .collect()
+}
+
+fn to_mesh(polygons: Vec<Polygon>, attributes: &VertexAttributes) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut sources = Vec::new();
+    let mut faces = Vec::new();
+    for polygon in polygons {
+        let first = vertices.len();
+        vertices.extend(polygon.points.iter().map(|p| Vec4::new_point(p[0] as f32, p[1] as f32, p[2] as f32)));
+        sources.extend(polygon.sources);
+        faces.push(Face::new((first..vertices.len()).collect()));
+    }
+    let mut mesh = Mesh::new(vertices, faces);
+    mesh.attributes = attributes.blend(&sources);
+    // faces of the second mesh can be turned inside out, so its normals would point the wrong way
+    mesh.attributes.normals.clear();
+    mesh.weld(EPSILON as f32 * 10.0);
+    mesh
+}
+
+/// The sources of the point `t` of the way from the point of `a` to that of `b`
+fn mix(a: &Sources, b: &Sources, t: f32) -> Sources {
+    let mut mixed: Sources = a.iter().map(|&(i, weight)| (i, weight * (1.0 - t))).collect();
+    for &(i, weight) in b.iter() {
+        match mixed.iter_mut().find(|(j, _)| *j == i) {
+            Some((_, mixed_weight)) => *mixed_weight += weight * t,
+            None => mixed.push((i, weight * t)),
+        }
+    }
+    mixed
+}
@@ -0,0 +1,60 @@
+use std::f32::consts::PI;
+use crate::vec4::Vec4;
+
+/// Parametric curves to sweep tubes along, evaluated for t in [0, 1]
+pub enum Curve {
+    TorusKnot(u32, u32, f32, f32), // p, q, radius, ring_radius; winds p times around the z axis and q times through the hole
+    Helix(f32, f32, f32), // radius, pitch, turns
+    Spring(f32, f32, f32, f32), // radius, pitch, turns, end_pitch; the first and last turn close up to end_pitch
+}
+
+impl Curve {
+    pub fn point(&self, t: f32) -> Vec4 {
+        match self {
+            Curve::TorusKnot(p, q, radius, ring_radius) => {
+                let angle = t * 2.0*PI;
+                let (p_angle, q_angle) = (*p as f32 * angle, *q as f32 * angle);
+                let distance = radius + ring_radius * q_angle.cos();
+                Vec4::new_point(distance * p_angle.cos(), distance * p_angle.sin(), ring_radius * q_angle.sin())
+            }
+            Curve::Helix(radius, pitch, turns) => {
+                let turn = t * turns;
+                let angle = turn * 2.0*PI;
+                Vec4::new_point(radius * angle.cos(), radius * angle.sin(), pitch * turn)
+            }
+            Curve::Spring(radius, pitch, turns, end_pitch) => {
+                let turns = turns.max(2.0);
+                let turn = t * turns;
+                let angle = turn * 2.0*PI;
+                // the pitch ramps up linearly over the first turn and down over the last one,
+                // the height is its integral
+                let ramp = |s: f32| if s < 1.0 { s*s / 2.0 } else { s - 0.5 };
+                let ramped_turns = ramp(turn) + ramp(turns) - ramp(turns - turn) - turn;
+                let z = end_pitch * turn + (pitch - end_pitch) * ramped_turns;
+                Vec4::new_point(radius * angle.cos(), radius * angle.sin(), z)
+            }
+        }
+    }
+
+    /// Whether the curve ends where it started
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Curve::TorusKnot(..))
+    }
+}
+
+#[test]
+fn test_curves() {
+    let knot = Curve::TorusKnot(2, 3, 2.0, 1.0);
+    assert!(knot.is_closed());
+    assert!(knot.point(0.0).sub(&knot.point(1.0)).length() < 1e-4);
+    assert!((knot.point(0.0).x() - 3.0).abs() < 1e-6);
+
+    let helix = Curve::Helix(1.0, 0.5, 4.0);
+    assert!((helix.point(1.0).z() - 2.0).abs() < 1e-6);
+
+    // a spring is as high as its turns at full pitch, minus half a turn's difference at both ends
+    let spring = Curve::Spring(1.0, 1.0, 5.0, 0.2);
+    assert!((spring.point(1.0).z() - (5.0 - 0.8)).abs() < 1e-5);
+    // its first turn is flatter than a helix of the same pitch
+    assert!(spring.point(0.1).z() < 0.5 * 0.5);
+}
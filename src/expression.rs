@@ -0,0 +1,226 @@
+/// A math expression in x, y and z, e.g. "x^2 + y^2 + z^2 - 1" or "sin(x) * cos(y) - z".
+/// Supports + - * / ^, parentheses, the constant pi and the functions
+/// sin, cos, tan, sqrt, abs, exp, ln, min and max.
+#[derive(Debug)]
+pub enum Expression {
+    Number(f32),
+    X,
+    Y,
+    Z,
+    Neg(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Pow(Box<Expression>, Box<Expression>),
+    Function(String, Vec<Expression>),
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser{tokens, pos: 0};
+        let expression = parser.parse_sum()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(format!("unexpected {:?} in expression: {text}", parser.tokens[parser.pos]));
+        }
+        Ok(expression)
+    }
+
+    pub fn evaluate(&self, x: f32, y: f32, z: f32) -> f32 {
+        match self {
+            Expression::Number(val) => *val,
+            Expression::X => x,
+            Expression::Y => y,
+            Expression::Z => z,
+            Expression::Neg(a) => -a.evaluate(x, y, z),
+            Expression::Add(a, b) => a.evaluate(x, y, z) + b.evaluate(x, y, z),
+            Expression::Sub(a, b) => a.evaluate(x, y, z) - b.evaluate(x, y, z),
+            Expression::Mul(a, b) => a.evaluate(x, y, z) * b.evaluate(x, y, z),
+            Expression::Div(a, b) => a.evaluate(x, y, z) / b.evaluate(x, y, z),
+            Expression::Pow(a, b) => a.evaluate(x, y, z).powf(b.evaluate(x, y, z)),
+            Expression::Function(name, args) => {
+                let args: Vec<f32> = args.iter().map(|arg| arg.evaluate(x, y, z)).collect();
+                match name.as_str() {
+                    "sin" => args[0].sin(),
+                    "cos" => args[0].cos(),
+                    "tan" => args[0].tan(),
+                    "sqrt" => args[0].sqrt(),
+                    "abs" => args[0].abs(),
+                    "exp" => args[0].exp(),
+                    "ln" => args[0].ln(),
+                    "min" => args[0].min(args[1]),
+                    _ => args[0].max(args[1]),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Operator(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        }
+        else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let number = number.parse::<f32>().map_err(|_| format!("invalid number: {number}"))?;
+            tokens.push(Token::Number(number));
+        }
+        else if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        }
+        else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Operator(c));
+            i += 1;
+        }
+        else {
+            return Err(format!("unexpected character {c} in expression: {text}"));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_is(&self, operator: char) -> bool {
+        self.tokens.get(self.pos) == Some(&Token::Operator(operator))
+    }
+
+    fn expect(&mut self, operator: char) -> Result<(), String> {
+        if !self.next_is(operator) {
+            return Err(format!("expected {operator} in expression"));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<Expression, String> {
+        let mut result = self.parse_product()?;
+        loop {
+            if self.next_is('+') {
+                self.pos += 1;
+                result = Expression::Add(Box::new(result), Box::new(self.parse_product()?));
+            }
+            else if self.next_is('-') {
+                self.pos += 1;
+                result = Expression::Sub(Box::new(result), Box::new(self.parse_product()?));
+            }
+            else {
+                return Ok(result);
+            }
+        }
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn parse_product(&mut self) -> Result<Expression, String> {
+        let mut result = self.parse_unary()?;
+        loop {
+            if self.next_is('*') {
+                self.pos += 1;
+                result = Expression::Mul(Box::new(result), Box::new(self.parse_unary()?));
+            }
+            else if self.next_is('/') {
+                self.pos += 1;
+                result = Expression::Div(Box::new(result), Box::new(self.parse_unary()?));
+            }
+            else {
+                return Ok(result);
+            }
+        }
+    }
+
+    // unary := '-' unary | power
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if self.next_is('-') {
+            self.pos += 1;
+            return Ok(Expression::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_power()
+    }
+
+    // power := primary ('^' unary)?, so -x^2 is -(x^2) and x^-1 is allowed
+    fn parse_power(&mut self) -> Result<Expression, String> {
+        let base = self.parse_primary()?;
+        if self.next_is('^') {
+            self.pos += 1;
+            return Ok(Expression::Pow(Box::new(base), Box::new(self.parse_unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        let token = self.tokens.get(self.pos).ok_or("expression ends unexpectedly")?;
+        self.pos += 1;
+
+        match token {
+            Token::Number(val) => Ok(Expression::Number(*val)),
+            Token::Operator('(') => {
+                let result = self.parse_sum()?;
+                self.expect(')')?;
+                Ok(result)
+            }
+            Token::Name(name) => {
+                let name = name.clone();
+                match name.as_str() {
+                    "x" => Ok(Expression::X),
+                    "y" => Ok(Expression::Y),
+                    "z" => Ok(Expression::Z),
+                    "pi" => Ok(Expression::Number(std::f32::consts::PI)),
+                    "sin" | "cos" | "tan" | "sqrt" | "abs" | "exp" | "ln" | "min" | "max" => {
+                        let args_amt = if name == "min" || name == "max" { 2 } else { 1 };
+                        self.expect('(')?;
+                        let mut args = vec![self.parse_sum()?];
+                        while args.len() < args_amt {
+                            self.expect(',')?;
+                            args.push(self.parse_sum()?);
+                        }
+                        self.expect(')')?;
+                        Ok(Expression::Function(name, args))
+                    }
+                    _ => Err(format!("unknown name in expression: {name}")),
+                }
+            }
+            Token::Operator(c) => Err(format!("unexpected {c} in expression")),
+        }
+    }
+}
+
+#[test]
+fn test_expression() {
+    let expression = Expression::parse("x^2 + y^2 + z^2 - 1").unwrap();
+    assert_eq!(expression.evaluate(1.0, 2.0, 3.0), 13.0);
+
+    let expression = Expression::parse("-x^2 * 2 + max(y, 3) / (1 + 1) - sqrt(z)").unwrap();
+    assert_eq!(expression.evaluate(2.0, 5.0, 4.0), -8.0 + 2.5 - 2.0);
+
+    assert!(Expression::parse("x +").is_err());
+    assert!(Expression::parse("foo(x)").is_err());
+    assert!(Expression::parse("(x").is_err());
+}
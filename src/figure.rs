@@ -1,16 +1,50 @@
+use std::collections::{HashMap, HashSet};
 use std::f32::consts::PI;
+use std::rc::Rc;
 use crate::{Color, vec4};
 use vec4::Vec4;
 use crate::matrix4::Matrix4;
+use crate::vec2::Vec2;
+use crate::l_system::LSystem;
+use crate::heightmap::Heightmap;
+use crate::implicit::ImplicitField;
+use crate::marching_cubes;
+use crate::patch::ParametricSurface;
+use crate::convex_hull;
+use crate::curve::Curve;
+use crate::triangulation::triangulate_polygon;
+use crate::subdivision;
+use crate::simplify;
+use crate::smoothing;
+use crate::validation::{self, ValidationReport};
+use crate::csg::{self, CsgOperation};
+use crate::bounds::{Aabb, BoundingSphere};
+use crate::slicing::{self, Plane, Polyline};
+use crate::uv_mapping::{self, UvProjection};
+use crate::attributes::VertexAttributes;
+use crate::mass_properties::{self, Tensor};
+use crate::random::Random;
 
+/// A mesh placed in the world with a material, the mesh can be shared by many figures
 #[derive(Clone)]
 pub struct Figure {
-    pub mesh: Mesh,
+    pub mesh: Rc<Mesh>,
+    pub bounding_sphere: Option<BoundingSphere>, // around the mesh before the transformation, shared by its copies
+    pub transform: Matrix4,
     pub ambient_reflection: Color,
     pub diffuse_reflection: Color,
     pub specular_reflection: Color,
 }
 
+impl Figure {
+    /// A copy of the mesh as it is placed in the world
+    pub fn world_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::clone(&self.mesh);
+        mesh.transform(&self.transform);
+        mesh
+    }
+}
+
 #[derive(Clone)]
 pub struct Face {
     // indexes in points from Figure, stored counter clock wise if you
@@ -19,18 +53,46 @@ pub struct Face {
 }
 
 impl Face{
-    fn new(indexes: Vec<usize>) -> Self {
+    pub fn new(indexes: Vec<usize>) -> Self {
         Self{indexes}
     }
 }
 
+/// Corners of the unit cube, corner i sits at (i&1, (i>>1)&1, (i>>2)&1)
+pub(crate) const CUBE_CORNERS: [[u32; 3]; 8] = [
+    [0,0,0], [1,0,0], [0,1,0], [1,1,0],
+    [0,0,1], [1,0,1], [0,1,1], [1,1,1],
+];
+
+/// Faces of the unit cube in the order -x, +x, -y, +y, -z, +z
+pub(crate) const CUBE_FACES: [[usize; 4]; 6] = [
+    [0,4,6,2], [1,3,7,5],
+    [0,1,5,4], [2,6,7,3],
+    [0,2,3,1], [4,5,7,6],
+];
+
+/// How much every face around a vertex counts towards the vertex normal
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NormalWeighting {
+    Area, // by the area of the face
+    Angle, // by the angle of the face's corner at the vertex, independent of how the faces are split
+}
+
 #[derive(Clone)]
 pub struct Mesh {
     pub vertices: Vec<Vec4>,
     pub faces: Vec<Face>,
+    // normals, texture coordinates, colors and the like, kept in step with the vertices
+    pub attributes: VertexAttributes,
+    // edges that stay sharp when the mesh is subdivided, as pairs of vertex indexes
+    pub creases: Vec<(usize, usize)>,
 }
 
 impl Mesh {
+    pub fn new(vertices: Vec<Vec4>, faces: Vec<Face>) -> Self {
+        Self{vertices, faces, attributes: VertexAttributes::default(), creases: Vec::new()}
+    }
+
     pub fn new_tetrahedron() -> Self {
         let points: Vec<Vec4> = vec![
             Vec4::new_point(1.0,-1.0,-1.0),
@@ -46,12 +108,210 @@ impl Mesh {
             Face::new(vec![1,3,2])
         ];
 
-        Self{ vertices: points, faces }
+        Self::new(points, faces)
+    }
+
+    pub fn new_cube() -> Self {
+        let vertices = CUBE_CORNERS.iter()
+            .map(|c| Vec4::new_point(c[0] as f32 * 2.0 - 1.0, c[1] as f32 * 2.0 - 1.0, c[2] as f32 * 2.0 - 1.0))
+            .collect();
+        let faces = CUBE_FACES.iter().map(|f| Face::new(f.to_vec())).collect();
+
+        Self::new(vertices, faces)
     }
 
-    /// radius is distance from torus center to center of a ring
+    pub fn new_octahedron() -> Self {
+        let vertices = vec![
+            Vec4::new_point(1.0,0.0,0.0),
+            Vec4::new_point(0.0,1.0,0.0),
+            Vec4::new_point(-1.0,0.0,0.0),
+            Vec4::new_point(0.0,-1.0,0.0),
+            Vec4::new_point(0.0,0.0,1.0),
+            Vec4::new_point(0.0,0.0,-1.0),
+        ];
+
+        let mut faces = Vec::new();
+        for i in 0..4 {
+            faces.push(Face::new(vec![i, (i+1)%4, 4]));
+            faces.push(Face::new(vec![(i+1)%4, i, 5]));
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// Two pentagonal rings of five points with a point above and below
+    pub fn new_icosahedron() -> Self {
+        let mut vertices = vec![Vec4::new_point(0.0, 0.0, 5.0_f32.sqrt()/2.0)];
+        for i in 0..5 {
+            let angle = i as f32 * 2.0*PI/5.0;
+            vertices.push(Vec4::new_point(angle.cos(), angle.sin(), 0.5));
+        }
+        for i in 0..5 {
+            let angle = PI/5.0 + i as f32 * 2.0*PI/5.0;
+            vertices.push(Vec4::new_point(angle.cos(), angle.sin(), -0.5));
+        }
+        vertices.push(Vec4::new_point(0.0, 0.0, -5.0_f32.sqrt()/2.0));
+
+        let mut faces = Vec::new();
+        for i in 0..5 {
+            let (upper, next_upper) = (1 + i, 1 + (i+1)%5);
+            let (lower, next_lower) = (6 + i, 6 + (i+1)%5);
+            faces.push(Face::new(vec![0, upper, next_upper]));
+            faces.push(Face::new(vec![upper, lower, next_upper]));
+            faces.push(Face::new(vec![next_upper, lower, next_lower]));
+            faces.push(Face::new(vec![11, next_lower, lower]));
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// The dual of the icosahedron: a point in the center of every icosahedron face
+    /// and a pentagon around every icosahedron vertex
+    pub fn new_dodecahedron() -> Self {
+        let icosahedron = Self::new_icosahedron();
+
+        let vertices = icosahedron.faces.iter()
+            .map(|face| {
+                let sum = face.indexes.iter()
+                    .fold(Vec4::new_vec4(0.0, 0.0, 0.0), |sum, &i| sum.add(&icosahedron.vertices[i]));
+                let center = sum.scale(1.0/3.0);
+                Vec4::new_point(center.x(), center.y(), center.z())
+            })
+            .collect();
+
+        let mut faces = Vec::new();
+        for vertex_i in 0..icosahedron.vertices.len() {
+            // the face after (v, a, b) going counter clock wise around v is the face (v, b, c)
+            let next_around_vertex = |face_i: usize| -> (usize, usize) {
+                let indexes = &icosahedron.faces[face_i].indexes;
+                let pos = indexes.iter().position(|&i| i == vertex_i).unwrap();
+                (indexes[(pos+1)%3], indexes[(pos+2)%3])
+            };
+
+            let first_face_i = icosahedron.faces.iter()
+                .position(|face| face.indexes.contains(&vertex_i))
+                .unwrap();
+
+            let mut indexes = vec![first_face_i];
+            let mut face_i = first_face_i;
+            loop {
+                let (_, b) = next_around_vertex(face_i);
+                face_i = (0..icosahedron.faces.len())
+                    .find(|&f| icosahedron.faces[f].indexes.contains(&vertex_i) && next_around_vertex(f).0 == b)
+                    .unwrap();
+                if face_i == first_face_i { break; }
+                indexes.push(face_i);
+            }
+            faces.push(Face::new(indexes));
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// Replaces the figure with copies scaled down by `fractal_scale`, one at every vertex so that
+    /// vertex i of the copy coincides with vertex i of the figure, and repeats this `iterations` times.
+    pub fn new_fractal(base: &Mesh, iterations: u32, fractal_scale: f32) -> Self {
+        // every copy is the base scaled by a factor followed by an offset
+        let mut copies = vec![(1.0_f32, Vec4::new_vec4(0.0, 0.0, 0.0))];
+
+        for _ in 0..iterations {
+            let mut next_copies = Vec::with_capacity(copies.len() * base.vertices.len());
+            for (scale, offset) in copies.iter() {
+                let next_scale = scale / fractal_scale;
+                for vertex in base.vertices.iter() {
+                    let next_offset = offset.add(&vertex.scale(scale - next_scale));
+                    next_copies.push((next_scale, Vec4::new_vec4(next_offset.x(), next_offset.y(), next_offset.z())));
+                }
+            }
+            copies = next_copies;
+        }
+
+        let mut vertices = Vec::with_capacity(copies.len() * base.vertices.len());
+        let mut faces = Vec::with_capacity(copies.len() * base.faces.len());
+
+        for (copy_i, (scale, offset)) in copies.iter().enumerate() {
+            let first_index = copy_i * base.vertices.len();
+            vertices.extend(base.vertices.iter().map(|v| v.scale(*scale).add(offset)));
+            faces.extend(base.faces.iter().map(|face| {
+                Face::new(face.indexes.iter().map(|i| first_index + i).collect())
+            }));
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// A cube fitting in [-1,1]^3 with its center and face centers carved out, repeated
+    /// for every remaining sub cube `iterations` times. Faces between sub cubes are left out.
+    pub fn new_menger_sponge(iterations: u32) -> Self {
+        let cells_per_side = 3_u32.pow(iterations);
+
+        fn is_filled(mut cell: [u32; 3], iterations: u32) -> bool {
+            for _ in 0..iterations {
+                if cell.iter().filter(|&&c| c % 3 == 1).count() >= 2 {
+                    return false;
+                }
+                cell = cell.map(|c| c / 3);
+            }
+            true
+        }
+
+        // collect the filled cells by recursing into the 20 remaining sub cubes
+        let mut cells = vec![[0_u32; 3]];
+        for _ in 0..iterations {
+            let mut next_cells = Vec::with_capacity(cells.len() * 20);
+            for cell in cells.iter() {
+                for sub_cell in 0..27 {
+                    let sub = [sub_cell % 3, (sub_cell / 3) % 3, sub_cell / 9];
+                    if sub.iter().filter(|&&c| c == 1).count() >= 2 { continue; }
+                    next_cells.push([cell[0]*3 + sub[0], cell[1]*3 + sub[1], cell[2]*3 + sub[2]]);
+                }
+            }
+            cells = next_cells;
+        }
+
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+        // vertex index of every grid point, usize::MAX while the point isn't used yet
+        let grid_points_per_side = cells_per_side as usize + 1;
+        let mut grid_point_indexes = vec![usize::MAX; grid_points_per_side.pow(3)];
+
+        let cell_size = 2.0 / cells_per_side as f32;
+        let neighbour_offsets: [[i64; 3]; 6] = [[-1,0,0], [1,0,0], [0,-1,0], [0,1,0], [0,0,-1], [0,0,1]];
+
+        for cell in cells.iter() {
+            for (face_i, offset) in neighbour_offsets.iter().enumerate() {
+                let neighbour = [0, 1, 2].map(|axis| cell[axis] as i64 + offset[axis]);
+                let on_boundary = neighbour.iter().any(|&c| c < 0 || c >= cells_per_side as i64);
+                if !on_boundary && is_filled(neighbour.map(|c| c as u32), iterations) {
+                    continue;
+                }
+
+                let indexes = CUBE_FACES[face_i].iter().map(|&corner_i| {
+                    let corner = CUBE_CORNERS[corner_i];
+                    let grid_point = [0, 1, 2].map(|axis| (cell[axis] + corner[axis]) as usize);
+                    let grid_i = (grid_point[2] * grid_points_per_side + grid_point[1]) * grid_points_per_side + grid_point[0];
+                    if grid_point_indexes[grid_i] == usize::MAX {
+                        grid_point_indexes[grid_i] = vertices.len();
+                        vertices.push(Vec4::new_point(
+                            grid_point[0] as f32 * cell_size - 1.0,
+                            grid_point[1] as f32 * cell_size - 1.0,
+                            grid_point[2] as f32 * cell_size - 1.0,
+                        ));
+                    }
+                    grid_point_indexes[grid_i]
+                }).collect();
+                faces.push(Face::new(indexes));
+            }
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// radius is distance from torus center to center of a ring. The texture coordinates are the
+    /// angles around the torus and around the ring, from 0 up to 1 where they wrap around.
     pub fn new_torus(radius: f32, ring_radius: f32, rings_amt: u32, ring_points_amt: u32) -> Self {
         let mut vertices = Vec::new();
+        let mut uvs = Vec::new();
         let mut faces = Vec::new();
 
         for ring_i in 0..rings_amt {
@@ -60,9 +320,10 @@ impl Mesh {
             for ring_segment_i in 0..ring_points_amt {
                 let ring_segment_angle = ring_segment_i as f32 * (2.0*PI / ring_points_amt as f32);
                 let z = ring_segment_angle.sin() * ring_radius;
-                let x = ring_angle.cos() * (radius+ring_segment_angle.cos());
-                let y = ring_angle.sin() * (radius+ring_segment_angle.cos());
+                let x = ring_angle.cos() * (radius + ring_segment_angle.cos() * ring_radius);
+                let y = ring_angle.sin() * (radius + ring_segment_angle.cos() * ring_radius);
                 vertices.push(Vec4::new_point(x,y,z));
+                uvs.push(Vec2::new(ring_i as f32 / rings_amt as f32, ring_segment_i as f32 / ring_points_amt as f32));
             }
         }
 
@@ -70,8 +331,8 @@ impl Mesh {
             (i * ring_points_amt + j).try_into().unwrap()
         }
 
-        for i in 0..ring_points_amt {
-            for j in 0..rings_amt {
+        for i in 0..rings_amt {
+            for j in 0..ring_points_amt {
                 // the vertices in the face are listed counter clock wise
                 let face = Face::new(
                     vec![
@@ -84,29 +345,1387 @@ impl Mesh {
             }
         }
 
-        Self{vertices, faces}
+        let mut mesh = Self::new(vertices, faces);
+        mesh.attributes.uvs = uvs;
+        mesh
     }
 
-    pub fn triangulate(&mut self) {
-        let mut new_faces = Vec::new();
+    /// Sweeps a circle of `tube_radius` along the curve, sampled in `segments` steps, with `sides` points
+    /// on every circle. The circles are oriented with rotation minimizing frames (double reflection), so the
+    /// tube doesn't twist more than the curve itself. Along a closed curve the twist that remains after one
+    /// round is spread over the whole tube so the ends line up, an open curve gets caps at both ends.
+    pub fn new_tube_sweep(curve: &Curve, tube_radius: f32, segments: u32, sides: u32) -> Self {
+        let closed = curve.is_closed();
+        let segments = segments.max(1) as usize;
+        let sides = sides.max(3) as usize;
+        let rings_amt = if closed { segments } else { segments + 1 };
 
-        for face in self.faces.iter() {
-            if face.indexes.len() <= 3 { continue; }
-            for i in 2..=face.indexes.len()-1 {
-                new_faces.push(Face::new(vec![
-                    face.indexes[0],
-                    face.indexes[i-1],
-                    face.indexes[i],
+        let points: Vec<Vec4> = (0..rings_amt).map(|i| curve.point(i as f32 / segments as f32)).collect();
+        let tangents: Vec<Vec4> = (0..rings_amt)
+            .map(|i| {
+                let (previous, next) = if closed {
+                    ((i + rings_amt - 1) % rings_amt, (i + 1) % rings_amt)
+                }
+                else {
+                    (i.saturating_sub(1), (i + 1).min(rings_amt - 1))
+                };
+                points[next].sub(&points[previous]).normalize()
+            })
+            .collect();
+
+        // start with the axis least aligned with the tangent, made perpendicular to it
+        let first_tangent = tangents[0];
+        let axis = [Vec4::new_vec4(1.0, 0.0, 0.0), Vec4::new_vec4(0.0, 1.0, 0.0), Vec4::new_vec4(0.0, 0.0, 1.0)]
+            .into_iter()
+            .min_by(|a, b| a.dot(&first_tangent).abs().total_cmp(&b.dot(&first_tangent).abs()))
+            .unwrap();
+        let mut normals = vec![axis.sub(&first_tangent.scale(axis.dot(&first_tangent))).normalize()];
+
+        let reflect = |v: &Vec4, mirror_normal: &Vec4, length_squared: f32| {
+            v.sub(&mirror_normal.scale(2.0 * mirror_normal.dot(v) / length_squared))
+        };
+        let frames_amt = if closed { rings_amt + 1 } else { rings_amt };
+        for i in 1..frames_amt {
+            let (previous_i, i) = (i - 1, i % rings_amt);
+            let step = points[i].sub(&points[previous_i]);
+            let step_length_squared = step.dot(&step);
+            if step_length_squared < 1e-12 {
+                normals.push(normals[previous_i]);
+                continue;
+            }
+            let reflected_normal = reflect(&normals[previous_i], &step, step_length_squared);
+            let reflected_tangent = reflect(&tangents[previous_i], &step, step_length_squared);
+            let tangent_difference = tangents[i].sub(&reflected_tangent);
+            let difference_length_squared = tangent_difference.dot(&tangent_difference);
+            normals.push(if difference_length_squared < 1e-12 {
+                reflected_normal
+            }
+            else {
+                reflect(&reflected_normal, &tangent_difference, difference_length_squared)
+            });
+        }
+
+        if closed {
+            // the frame carried all around the curve is turned by some angle compared to the first one
+            let (first, last) = (normals[0], normals.pop().unwrap());
+            let twist = first.cross(&last).dot(&first_tangent).atan2(first.dot(&last));
+            for (i, normal) in normals.iter_mut().enumerate() {
+                let angle = -twist * i as f32 / rings_amt as f32;
+                let binormal = tangents[i].cross(normal);
+                *normal = normal.scale(angle.cos()).add(&binormal.scale(angle.sin()));
+            }
+        }
+
+        let mut vertices = Vec::with_capacity(rings_amt * sides);
+        for ring_i in 0..rings_amt {
+            let normal = normals[ring_i];
+            let binormal = tangents[ring_i].cross(&normal);
+            for side_i in 0..sides {
+                let angle = side_i as f32 * (2.0*PI / sides as f32);
+                let offset = normal.scale(angle.cos() * tube_radius).add(&binormal.scale(angle.sin() * tube_radius));
+                vertices.push(points[ring_i].add(&offset));
+            }
+        }
+
+        let mut faces = Vec::new();
+        let index = |ring_i: usize, side_i: usize| (ring_i % rings_amt) * sides + side_i % sides;
+        for ring_i in 0..segments {
+            for side_i in 0..sides {
+                faces.push(Face::new(vec![
+                    index(ring_i, side_i),
+                    index(ring_i, side_i + 1),
+                    index(ring_i + 1, side_i + 1),
+                    index(ring_i + 1, side_i),
+                ]));
+            }
+        }
+        if !closed {
+            faces.push(Face::new((0..sides).rev().map(|side_i| index(0, side_i)).collect()));
+            faces.push(Face::new((0..sides).map(|side_i| index(segments, side_i)).collect()));
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// Revolves a profile around the z axis in `steps` steps. Profile points are given as
+    /// (distance to the axis, height) and should be listed from bottom to top so the faces
+    /// point outward. Points on the axis are shared by the whole revolution. Fewer than 3 steps
+    /// are taken as 3. The texture coordinates are the angle around the axis and how far along the
+    /// profile the point is.
+    pub fn new_lathe(profile: &[Vec2], steps: u32) -> Self {
+        let steps = steps.max(3);
+        let mut vertices = Vec::new();
+        let mut uvs = Vec::new();
+        let mut faces = Vec::new();
+        let v = |j: usize| if profile.len() > 1 { j as f32 / (profile.len() - 1) as f32 } else { 0.0 };
+
+        // ring_indexes[j][i] is the vertex of profile point j at revolution step i
+        let mut ring_indexes: Vec<Vec<usize>> = Vec::new();
+
+        for (j, point) in profile.iter().enumerate() {
+            if point.x() == 0.0 {
+                vertices.push(Vec4::new_point(0.0, 0.0, point.y()));
+                uvs.push(Vec2::new(0.5, v(j)));
+                ring_indexes.push(vec![vertices.len()-1; steps as usize]);
+                continue;
+            }
+
+            let mut ring = Vec::new();
+            for step_i in 0..steps {
+                let angle = step_i as f32 * (2.0*PI / steps as f32);
+                vertices.push(Vec4::new_point(angle.cos() * point.x(), angle.sin() * point.x(), point.y()));
+                uvs.push(Vec2::new(step_i as f32 / steps as f32, v(j)));
+                ring.push(vertices.len()-1);
+            }
+            ring_indexes.push(ring);
+        }
+
+        for j in 1..ring_indexes.len() {
+            for i in 0..steps as usize {
+                let next_i = (i+1) % steps as usize;
+                let mut indexes = vec![
+                    ring_indexes[j-1][i],
+                    ring_indexes[j-1][next_i],
+                    ring_indexes[j][next_i],
+                    ring_indexes[j][i],
+                ];
+                // points on the axis collapse a quad into a triangle
+                indexes.dedup();
+                if indexes.len() >= 3 {
+                    faces.push(Face::new(indexes));
+                }
+            }
+        }
+
+        let mut mesh = Self::new(vertices, faces);
+        mesh.attributes.uvs = uvs;
+        mesh
+    }
+
+    /// Sweeps a closed profile, lying in the xy plane and listed counter clock wise, along
+    /// `direction` in `steps` steps. Over the length of the sweep the profile is rotated by
+    /// `twist_rad` around its origin and scaled from 1 to `end_scale`. Both ends are capped. 0 steps
+    /// are taken as 1.
+    pub fn new_extrusion(profile: &[Vec2], direction: &Vec4, steps: u32, twist_rad: f32, end_scale: f32) -> Self {
+        let steps = steps.max(1);
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+
+        let profile_len = profile.len();
+
+        for step_i in 0..=steps {
+            let t = step_i as f32 / steps as f32;
+            let (sin, cos) = (twist_rad * t).sin_cos();
+            let scale = 1.0 + (end_scale - 1.0) * t;
+            let offset = direction.scale(t);
+
+            for point in profile.iter() {
+                let x = (point.x()*cos - point.y()*sin) * scale;
+                let y = (point.x()*sin + point.y()*cos) * scale;
+                vertices.push(Vec4::new_point(x, y, 0.0).add(&offset));
+            }
+        }
+
+        for step_i in 0..steps as usize {
+            for j in 0..profile_len {
+                let next_j = (j+1) % profile_len;
+                faces.push(Face::new(vec![
+                    step_i * profile_len + j,
+                    step_i * profile_len + next_j,
+                    (step_i+1) * profile_len + next_j,
+                    (step_i+1) * profile_len + j,
                 ]));
             }
         }
 
+        // the bottom cap is seen from below, so its points are listed the other way around
+        faces.push(Face::new((0..profile_len).rev().collect()));
+        faces.push(Face::new((0..profile_len).map(|j| steps as usize * profile_len + j).collect()));
+
+        Self::new(vertices, faces)
+    }
+
+    /// Puts a thin tube with `tube_sides` sides around every line drawn by the 3D turtle
+    pub fn new_l_system_3d(l_system: &LSystem, tube_radius: f32, tube_sides: u32) -> Self {
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+
+        let sides = tube_sides as usize;
+
+        for segment in l_system.interpret_3d().iter() {
+            let first_index = vertices.len();
+
+            for end in [&segment.start, &segment.end] {
+                for side_i in 0..tube_sides {
+                    let angle = side_i as f32 * (2.0*PI / tube_sides as f32);
+                    let offset = segment.left.scale(angle.cos() * tube_radius)
+                        .add(&segment.up.scale(angle.sin() * tube_radius));
+                    vertices.push(end.add(&offset));
+                }
+            }
+
+            for side_i in 0..sides {
+                let next_side_i = (side_i+1) % sides;
+                faces.push(Face::new(vec![
+                    first_index + side_i,
+                    first_index + next_side_i,
+                    first_index + sides + next_side_i,
+                    first_index + sides + side_i,
+                ]));
+            }
+            faces.push(Face::new((0..sides).rev().map(|i| first_index + i).collect()));
+            faces.push(Face::new((0..sides).map(|i| first_index + sides + i).collect()));
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// A grid of quads centered around the origin in the xy plane, grid points are
+    /// `cell_size` apart and raised along z by their height times `height_scale`
+    pub fn new_terrain(heightmap: &Heightmap, cell_size: f32, height_scale: f32) -> Self {
+        let mut vertices = Vec::with_capacity(heightmap.width * heightmap.depth);
+        let mut faces = Vec::new();
+
+        let x_offset = (heightmap.width as f32 - 1.0) * cell_size / 2.0;
+        let y_offset = (heightmap.depth as f32 - 1.0) * cell_size / 2.0;
+
+        for y in 0..heightmap.depth {
+            for x in 0..heightmap.width {
+                vertices.push(Vec4::new_point(
+                    x as f32 * cell_size - x_offset,
+                    y as f32 * cell_size - y_offset,
+                    heightmap.height(x, y) * height_scale,
+                ));
+            }
+        }
+
+        // the heightmap spread over the texture
+        let uvs = (0..heightmap.depth)
+            .flat_map(|y| (0..heightmap.width).map(move |x| Vec2::new(
+                x as f32 / (heightmap.width.max(2) - 1) as f32,
+                y as f32 / (heightmap.depth.max(2) - 1) as f32,
+            )))
+            .collect();
+
+        let index = |x: usize, y: usize| y * heightmap.width + x;
+        for y in 0..heightmap.depth.saturating_sub(1) {
+            for x in 0..heightmap.width.saturating_sub(1) {
+                faces.push(Face::new(vec![index(x, y), index(x+1, y), index(x+1, y+1), index(x, y+1)]));
+            }
+        }
+
+        let mut mesh = Self::new(vertices, faces);
+        mesh.attributes.uvs = uvs;
+        mesh.attributes.scalars.insert("height".to_string(), heightmap.heights.clone());
+        mesh
+    }
+
+    /// Polygonizes the surface of the field with marching cubes, `resolution` cells along every axis
+    /// of the box between `min` and `max`
+    pub fn new_implicit(field: &ImplicitField, min: &Vec4, max: &Vec4, resolution: u32) -> Self {
+        marching_cubes::polygonize(|p| field.value(p), min, max, resolution)
+    }
+
+    /// Tessellates every surface in `resolution` by `resolution` quads per unit of its parameter domain.
+    /// The normals are the cross product of the derivatives along u and v, the tangents the derivative
+    /// along u, the texture coordinates are u and v scaled to run from 0 to 1 over every surface.
+    pub fn new_parametric_surfaces<S: ParametricSurface>(surfaces: &[S], resolution: u32) -> Self {
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut tangents = Vec::new();
+        let mut faces = Vec::new();
+
+        for surface in surfaces.iter() {
+            let (u_max, v_max) = surface.domain();
+            let u_steps = ((u_max * resolution as f32).round() as usize).max(1);
+            let v_steps = ((v_max * resolution as f32).round() as usize).max(1);
+            let first_index = vertices.len();
+
+            for v_i in 0..=v_steps {
+                for u_i in 0..=u_steps {
+                    let u = u_i as f32 / u_steps as f32 * u_max;
+                    let v = v_i as f32 / v_steps as f32 * v_max;
+                    let (position, du, dv) = surface.evaluate(u, v);
+                    vertices.push(position);
+                    uvs.push(Vec2::new(u_i as f32 / u_steps as f32, v_i as f32 / v_steps as f32));
+                    tangents.push(if du.length() > 0.0 { du.normalize() } else { du });
+
+                    let mut normal = du.cross(&dv);
+                    if normal.length() < 1e-6 {
+                        // a collapsed edge of the patch, like the tip of the teapot lid, has no tangent
+                        // plane of its own, so take it from just inside the patch
+                        let eps = 1e-3;
+                        let (_, du, dv) = surface.evaluate(u.clamp(eps, u_max - eps), v.clamp(eps, v_max - eps));
+                        normal = du.cross(&dv);
+                    }
+                    normals.push(normal.normalize());
+                }
+            }
+
+            let index = |u_i: usize, v_i: usize| first_index + v_i * (u_steps + 1) + u_i;
+            for v_i in 0..v_steps {
+                for u_i in 0..u_steps {
+                    faces.push(Face::new(vec![index(u_i, v_i), index(u_i+1, v_i), index(u_i+1, v_i+1), index(u_i, v_i+1)]));
+                }
+            }
+        }
+
+        let mut mesh = Self::new(vertices, faces);
+        mesh.attributes.normals = normals;
+        mesh.attributes.uvs = uvs;
+        mesh.attributes.tangents = tangents;
+        mesh
+    }
+
+    /// The smallest convex figure containing all points, built with quickhull.
+    /// Faces are counter clock wise seen from outside, triangles in the same plane are merged into one polygon.
+    pub fn new_convex_hull(points: &[Vec4]) -> Self {
+        convex_hull::quickhull(points)
+    }
+
+    /// A random convex rock: the hull of `points_amt` points between `1 - roughness` and 1 away from the origin
+    pub fn new_rock(points_amt: u32, roughness: f32, seed: u64) -> Self {
+        let mut random = Random::new(seed);
+        let mut points = Vec::new();
+
+        while points.len() < points_amt as usize {
+            let direction = Vec4::new_vec4(random.range_f32(-1.0, 1.0), random.range_f32(-1.0, 1.0), random.range_f32(-1.0, 1.0));
+            let length = direction.length();
+            // only directions inside the unit sphere are spread evenly over all directions
+            if !(1e-3..=1.0).contains(&length) { continue; }
+            let distance = random.range_f32(1.0 - roughness, 1.0);
+            let p = direction.scale(distance / length);
+            points.push(Vec4::new_point(p.x(), p.y(), p.z()));
+        }
+
+        Self::new_convex_hull(&points)
+    }
+
+    /// Splits every face into triangles, triangles are kept as they are. Concave faces are cut with
+    /// ear clipping. Returns the indexes of the faces that couldn't be triangulated, those are fanned
+    /// from their first corner when they have at least three.
+    pub fn triangulate(&mut self) -> Vec<usize> {
+        let mut new_faces = Vec::new();
+        let mut failed = Vec::new();
+
+        for (face_i, face) in self.faces.iter().enumerate() {
+            let corners: Vec<Vec4> = face.indexes.iter().map(|&i| self.vertices[i]).collect();
+            match triangulate_polygon(&corners) {
+                Ok(triangles) => {
+                    for [a, b, c] in triangles {
+                        new_faces.push(Face::new(vec![face.indexes[a], face.indexes[b], face.indexes[c]]));
+                    }
+                }
+                Err(()) => {
+                    failed.push(face_i);
+                    for i in 2..face.indexes.len() {
+                        new_faces.push(Face::new(vec![face.indexes[0], face.indexes[i-1], face.indexes[i]]));
+                    }
+                }
+            }
+        }
+
         self.faces = new_faces;
+        failed
     }
 
+    /// Normal of the face by Newell's method, pointing to the side where its corners go around counter
+    /// clock wise. Its length is twice the area of the face, so it is zero for faces without area.
+    pub fn face_normal(&self, face: &Face) -> Vec4 {
+        let mut normal = Vec4::new_vec4(0.0, 0.0, 0.0);
+        for (i, &index) in face.indexes.iter().enumerate() {
+            let p = self.vertices[index];
+            let q = self.vertices[face.indexes[(i+1) % face.indexes.len()]];
+            normal = normal.add(&Vec4::new_vec4(
+                (p.y() - q.y()) * (p.z() + q.z()),
+                (p.z() - q.z()) * (p.x() + q.x()),
+                (p.x() - q.x()) * (p.y() + q.y()),
+            ));
+        }
+        normal
+    }
+
+    /// Gives every vertex the weighted average normal of the faces around it. Where faces meet at an
+    /// angle bigger than `crease_angle_rad` the edge stays sharp: the vertex is split in one vertex per
+    /// side of the crease, each with its own normal. A crease angle of 0 gives every face its own vertices
+    /// unless it lies in the same plane as its neighbours, a crease angle of PI smooths everything.
+    pub fn compute_normals(&mut self, weighting: NormalWeighting, crease_angle_rad: f32) {
+        let face_normals: Vec<Vec4> = self.faces.iter().map(|face| self.face_normal(face)).collect();
+        let unit_face_normals: Vec<Vec4> = face_normals.iter()
+            .map(|n| if n.length() > 0.0 { n.normalize() } else { *n })
+            .collect();
+
+        // the (face, corner) pairs around every vertex
+        let mut corners = vec![Vec::new(); self.vertices.len()];
+        for (face_i, face) in self.faces.iter().enumerate() {
+            for (corner_i, &index) in face.indexes.iter().enumerate() {
+                corners[index].push((face_i, corner_i));
+            }
+        }
+
+        let weighted_normal = |face_i: usize, corner_i: usize| -> Vec4 {
+            match weighting {
+                NormalWeighting::Area => face_normals[face_i].scale(0.5),
+                NormalWeighting::Angle => {
+                    let indexes = &self.faces[face_i].indexes;
+                    let n = indexes.len();
+                    let p = self.vertices[indexes[corner_i]];
+                    let to_previous = self.vertices[indexes[(corner_i + n - 1) % n]].sub(&p);
+                    let to_next = self.vertices[indexes[(corner_i + 1) % n]].sub(&p);
+                    let lengths = to_previous.length() * to_next.length();
+                    if lengths <= 0.0 { return Vec4::new_vec4(0.0, 0.0, 0.0); }
+                    let angle = (to_previous.dot(&to_next) / lengths).clamp(-1.0, 1.0).acos();
+                    unit_face_normals[face_i].scale(angle)
+                }
+            }
+        };
+
+        let cos_crease = crease_angle_rad.cos() - 1e-6;
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        let mut normals = Vec::with_capacity(self.vertices.len());
+        let mut sources = Vec::with_capacity(self.vertices.len()); // the vertex every new one is split from
+        let mut faces = self.faces.clone();
+
+        for (vertex_i, vertex_corners) in corners.iter().enumerate() {
+            if vertex_corners.is_empty() {
+                vertices.push(self.vertices[vertex_i]);
+                normals.push(Vec4::new_vec4(0.0, 0.0, 0.0));
+                sources.push(vertex_i);
+                continue;
+            }
+
+            // the vertices this one is split in
+            let first_split = vertices.len();
+            for &(face_i, corner_i) in vertex_corners.iter() {
+                let mut normal = Vec4::new_vec4(0.0, 0.0, 0.0);
+                for &(other_face_i, other_corner_i) in vertex_corners.iter() {
+                    if other_face_i == face_i || unit_face_normals[face_i].dot(&unit_face_normals[other_face_i]) >= cos_crease {
+                        normal = normal.add(&weighted_normal(other_face_i, other_corner_i));
+                    }
+                }
+                let normal = if normal.length() > 0.0 { normal.normalize() } else { unit_face_normals[face_i] };
+
+                let index = (first_split..vertices.len())
+                    .find(|&i| normals[i].dot(&normal) > 1.0 - 1e-6)
+                    .unwrap_or_else(|| {
+                        vertices.push(self.vertices[vertex_i]);
+                        normals.push(normal);
+                        sources.push(vertex_i);
+                        vertices.len() - 1
+                    });
+                faces[face_i].indexes[corner_i] = index;
+            }
+        }
+
+        self.vertices = vertices;
+        self.faces = faces;
+        self.attributes = self.attributes.select(&sources);
+        self.attributes.normals = normals;
+        // the vertices along the creases are split now, the old indexes mean nothing anymore
+        self.creases.clear();
+    }
+
+    /// Marks the edges between two faces that meet at an angle bigger than `crease_angle_rad` as creases
+    pub fn mark_creases(&mut self, crease_angle_rad: f32) {
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (face_i, face) in self.faces.iter().enumerate() {
+            let n = face.indexes.len();
+            for i in 0..n {
+                let (a, b) = (face.indexes[i], face.indexes[(i+1)%n]);
+                edge_faces.entry((a.min(b), a.max(b))).or_default().push(face_i);
+            }
+        }
+
+        let cos_crease = crease_angle_rad.cos();
+        let mut creases: Vec<(usize, usize)> = edge_faces.into_iter()
+            .filter(|(_, faces)| {
+                faces.len() == 2 && {
+                    let n0 = self.face_normal(&self.faces[faces[0]]);
+                    let n1 = self.face_normal(&self.faces[faces[1]]);
+                    n0.dot(&n1) < cos_crease * n0.length() * n1.length()
+                }
+            })
+            .map(|(edge, _)| edge)
+            .collect();
+        creases.sort();
+        self.creases = creases;
+    }
+
+    /// Loop subdivision, every level splits every triangle in four and smooths the mesh. Faces that
+    /// aren't triangles are triangulated first. Boundaries and creases stay sharp and are smoothed
+    /// as curves of their own, points where more than two of them meet stay where they are.
+    pub fn subdivide_loop(&mut self, levels: u32) {
+        if self.faces.iter().any(|face| face.indexes.len() != 3) {
+            self.triangulate();
+        }
+        for _ in 0..levels {
+            *self = subdivision::loop_step(self);
+        }
+    }
+
+    /// Catmull-Clark subdivision, every level splits every face in quads and smooths the mesh.
+    /// Boundaries and creases are handled like in `subdivide_loop`.
+    pub fn subdivide_catmull_clark(&mut self, levels: u32) {
+        for _ in 0..levels {
+            *self = subdivision::catmull_clark_step(self);
+        }
+    }
+
+    /// Collapses edges until the mesh has at most `target_face_count` triangles, keeping the shape and
+    /// boundaries as well as possible. Faces that aren't triangles are triangulated first. Edges are only
+    /// collapsed when no face flips over, so the result can keep more faces than asked for.
+    pub fn simplify(&mut self, target_face_count: usize) {
+        if self.faces.iter().any(|face| face.indexes.len() != 3) {
+            self.triangulate();
+        }
+        *self = simplify::simplify(self, target_face_count);
+    }
+
+    /// Laplacian smoothing, every iteration moves every vertex `lambda` of the way toward the average of
+    /// its neighbours. Boundaries are smoothed as curves of their own, or stay where they are with
+    /// `pin_boundary`. Repeated often enough this shrinks the mesh, see `smooth_taubin`. Fails, leaving
+    /// the mesh as it is, when it isn't a surface half edges can describe.
+    pub fn smooth_laplacian(&mut self, iterations: u32, lambda: f32, pin_boundary: bool) -> Result<(), String> {
+        smoothing::smooth(self, (0..iterations).map(|_| lambda), pin_boundary)?;
+        self.attributes.normals.clear();
+        Ok(())
+    }
+
+    /// Taubin's smoothing without shrinking: every iteration is a Laplacian step by `lambda` followed by
+    /// one by `mu`, which is negative and a bit larger than `lambda` (like 0.5 and -0.53) to grow the
+    /// mesh back out again.
+    pub fn smooth_taubin(&mut self, iterations: u32, lambda: f32, mu: f32, pin_boundary: bool) -> Result<(), String> {
+        smoothing::smooth(self, (0..iterations).flat_map(|_| [lambda, mu]), pin_boundary)?;
+        self.attributes.normals.clear();
+        Ok(())
+    }
+
+    /// Merges vertices closer than `epsilon` to each other. Vertices are hashed in cells of `epsilon`,
+    /// so only the cells around a vertex have to be searched. The faces are pointed to the merged
+    /// vertices, corners that follow each other on the same vertex are joined, and faces left with
+    /// less than three corners or running through the same corners as an earlier face are dropped.
+    /// Merged vertices keep the attributes of the first of them.
+    pub fn weld(&mut self, epsilon: f32) {
+        let cell_size = epsilon.max(f32::MIN_POSITIVE);
+        let cell = |v: &Vec4| [v.x(), v.y(), v.z()].map(|c| (c / cell_size).floor() as i64);
+
+        let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut new_indexes = Vec::with_capacity(self.vertices.len());
+        let mut vertices: Vec<Vec4> = Vec::new();
+        let mut representatives = Vec::new();
+
+        for (vertex_i, vertex) in self.vertices.iter().enumerate() {
+            let [x, y, z] = cell(vertex);
+            let mut found = None;
+            'search: for dz in -1..=1 {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let Some(candidates) = cells.get(&[x + dx, y + dy, z + dz]) else { continue };
+                        if let Some(&i) = candidates.iter().find(|&&i| vertices[i].sub(vertex).length() <= epsilon) {
+                            found = Some(i);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+
+            let index = found.unwrap_or_else(|| {
+                vertices.push(*vertex);
+                representatives.push(vertex_i);
+                cells.entry([x, y, z]).or_default().push(vertices.len() - 1);
+                vertices.len() - 1
+            });
+            new_indexes.push(index);
+        }
+
+        let mut seen = HashSet::new();
+        let mut faces = Vec::with_capacity(self.faces.len());
+        for face in self.faces.iter() {
+            let mut indexes: Vec<usize> = face.indexes.iter().map(|&i| new_indexes[i]).collect();
+            indexes.dedup();
+            while indexes.len() > 1 && indexes.first() == indexes.last() {
+                indexes.pop();
+            }
+            if indexes.len() < 3 { continue; }
+
+            // the same cycle of corners starts at its smallest index
+            let start = (0..indexes.len()).min_by_key(|&i| indexes[i]).unwrap();
+            let mut cycle = indexes.clone();
+            cycle.rotate_left(start);
+            if seen.insert(cycle) {
+                faces.push(Face::new(indexes));
+            }
+        }
+
+        self.creases = self.creases.iter()
+            .map(|&(a, b)| (new_indexes[a], new_indexes[b]))
+            .filter(|(a, b)| a != b)
+            .collect();
+        self.vertices = vertices;
+        self.faces = faces;
+        self.attributes = self.attributes.select(&representatives);
+    }
+
+    /// Checks whether the mesh is a closed, consistently wound surface and reports what isn't
+    pub fn validate(&self) -> ValidationReport {
+        validation::validate(self)
+    }
+
+    /// Like `validate`, but vertices at the same place count as one, so vertices split apart for their
+    /// own normals or texture coordinates don't show up as holes
+    pub fn validate_surface(&self) -> ValidationReport {
+        let vertices_amt = self.vertices.len();
+        if self.faces.iter().any(|face| face.indexes.iter().any(|&i| i >= vertices_amt)) {
+            return self.validate();
+        }
+        let mut surface = Mesh::new(self.vertices.clone(), self.faces.clone());
+        let size = surface.aabb().map_or(0.0, |bounds| bounds.size().length());
+        surface.weld(1e-6 * size.max(1.0));
+        surface.validate()
+    }
+
+    /// Drops faces pointing to missing vertices or without area, fills holes of at most `max_hole_edges`
+    /// edges and winds all faces consistently, pointing outward on closed parts
+    pub fn repair(&mut self, max_hole_edges: usize) {
+        validation::repair(self, max_hole_edges);
+        self.attributes.normals.clear();
+        self.creases.clear();
+    }
+
+    /// Everything inside either mesh, both have to be closed
+    pub fn union(&self, other: &Mesh) -> Self {
+        csg::csg(self, other, CsgOperation::Union)
+    }
+
+    /// Everything inside both meshes
+    pub fn intersection(&self, other: &Mesh) -> Self {
+        csg::csg(self, other, CsgOperation::Intersection)
+    }
+
+    /// Everything inside this mesh but not inside the other one
+    pub fn difference(&self, other: &Mesh) -> Self {
+        csg::csg(self, other, CsgOperation::Difference)
+    }
+
+    /// The bounds are worked out from the vertices as they are, so they follow every transformation.
+    /// None for a mesh without vertices.
+    pub fn aabb(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.vertices)
+    }
+
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::from_points(&self.vertices)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        mass_properties::surface_area(self)
+    }
+
+    /// The volume the faces enclose, negative when they point inward. Only meaningful for a closed mesh.
+    pub fn volume(&self) -> f32 {
+        mass_properties::volume(self)
+    }
+
+    /// The center of mass of the enclosed solid, None when it encloses no volume
+    pub fn centroid(&self) -> Option<Vec4> {
+        mass_properties::centroid(self)
+    }
+
+    /// The inertia tensor of the enclosed solid around its centroid, for a density of 1
+    pub fn inertia_tensor(&self) -> Option<Tensor> {
+        mass_properties::inertia_tensor(self)
+    }
+
+    /// Colors every vertex by its value of the named scalar, blending from `low` at the smallest value
+    /// to `high` at the largest
+    pub fn color_by_scalar(&mut self, name: &str, low: &Color, high: &Color) -> Result<(), String> {
+        let values = self.attributes.scalars.get(name).ok_or(format!("the mesh has no scalar called \"{name}\""))?;
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        self.attributes.colors = values.iter().map(|&value| {
+            let t = if max > min { (value - min) / (max - min) } else { 0.5 };
+            Color::new(low.r() + (high.r() - low.r()) * t, low.g() + (high.g() - low.g()) * t, low.b() + (high.b() - low.b()) * t)
+        }).collect();
+        Ok(())
+    }
+
+    /// Replaces the texture coordinates with ones projected onto the mesh as it is now
+    pub fn project_uvs(&mut self, projection: UvProjection) {
+        self.attributes.uvs = uv_mapping::project(self, projection);
+    }
+
+    /// The cross section with the plane as polylines, closed loops for a closed mesh. Faces that
+    /// aren't triangles are cut as the triangles `triangulate` splits them in.
+    pub fn slice(&self, plane: &Plane) -> Vec<Polyline> {
+        if self.faces.iter().all(|face| face.indexes.len() == 3) {
+            return slicing::slice(self, plane);
+        }
+        let mut triangulated = Mesh::new(self.vertices.clone(), self.faces.clone());
+        triangulated.triangulate();
+        slicing::slice(&triangulated, plane)
+    }
+
+    /// Normals and tangents are transformed along with the vertices, which keeps them perpendicular and
+    /// tangent to the surface as long as the transformation doesn't scale differently along different axes
     pub fn transform(&mut self, t: &Matrix4) {
         for vertex in self.vertices.iter_mut() {
             *vertex = vertex.mul(t);
         }
+        self.attributes.transform(t);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_platonic_solids_and_fractals() {
+    let solids = [
+        (Mesh::new_tetrahedron(), 4, 4),
+        (Mesh::new_cube(), 8, 6),
+        (Mesh::new_octahedron(), 6, 8),
+        (Mesh::new_icosahedron(), 12, 20),
+        (Mesh::new_dodecahedron(), 20, 12),
+    ];
+    for (mesh, vertices_amt, faces_amt) in solids.iter() {
+        assert_eq!(mesh.vertices.len(), *vertices_amt);
+        assert_eq!(mesh.faces.len(), *faces_amt);
+        // every face points outward
+        assert!(mesh.volume() > 0.0);
+    }
+
+    let fractal = Mesh::new_fractal(&Mesh::new_tetrahedron(), 3, 2.0);
+    assert_eq!(fractal.vertices.len(), 4*4*4*4);
+    assert_eq!(fractal.faces.len(), 4*4*4*4);
+    // the corners of the fractal are the corners of the original figure
+    assert_eq!(fractal.vertices[0], Mesh::new_tetrahedron().vertices[0]);
+
+    let sponge = Mesh::new_menger_sponge(0);
+    assert_eq!(sponge.faces.len(), 6);
+    let sponge = Mesh::new_menger_sponge(1);
+    // 8 corner cubes with 3 outer faces, 12 edge cubes with 2 outer and 2 inner faces
+    assert_eq!(sponge.faces.len(), 8*3 + 12*4);
+    assert!((sponge.volume() - 8.0 * 20.0/27.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_l_system_3d_tubes() {
+    let l_system = LSystem::parse("
+        Alphabet = {F}
+        Draw = {F -> 1}
+        Rules = {F -> \"F+F\"}
+        Initiator = \"F\"
+        Angle = 90
+        Iterations = 2
+    ");
+    let mesh = Mesh::new_l_system_3d(&l_system, 0.1, 6);
+    assert_eq!(mesh.vertices.len(), 4 * 2*6);
+    assert_eq!(mesh.faces.len(), 4 * (6+2));
+    assert!(mesh.volume() > 0.0);
+}
+
+#[test]
+fn test_terrain() {
+    let mut image = bmp::Image::new(3, 2);
+    image.set_pixel(2, 0, bmp::Pixel::new(255, 255, 255));
+    image.save("test_heightmap.bmp").expect("writing heightmap failed");
+    let heightmap = Heightmap::from_bmp("test_heightmap.bmp");
+    std::fs::remove_file("test_heightmap.bmp").unwrap();
+
+    let terrain = Mesh::new_terrain(&heightmap, 2.0, 10.0);
+    assert_eq!(terrain.vertices.len(), 6);
+    assert_eq!(terrain.faces.len(), 2);
+    // the white top right pixel is the highest point
+    assert_eq!(terrain.vertices[5], Vec4::new_point(2.0, 1.0, 10.0));
+    assert_eq!(terrain.vertices[0], Vec4::new_point(-2.0, -1.0, 0.0));
+
+    let noise_terrain = Mesh::new_terrain(&Heightmap::from_noise(16, 8, 1, 0.1, 4, 2.0, 0.5), 1.0, 1.0);
+    assert_eq!(noise_terrain.faces.len(), 15*7);
+}
+
+#[test]
+fn test_implicit_surfaces() {
+    use crate::expression::Expression;
+    use crate::implicit::SdfPrimitive;
+    use std::collections::HashMap;
+
+    // every edge of a closed surface is used once in both directions
+    fn is_closed(mesh: &Mesh) -> bool {
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+        for face in mesh.faces.iter() {
+            for i in 0..face.indexes.len() {
+                let (a, b) = (face.indexes[i], face.indexes[(i+1) % face.indexes.len()]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += if a < b { 1 } else { -1 };
+            }
+        }
+        edges.values().all(|&balance| balance == 0)
+    }
+
+    let (min, max) = (Vec4::new_point(-1.5, -1.5, -1.5), Vec4::new_point(1.5, 1.5, 1.5));
+
+    let sphere = ImplicitField::Expression(Expression::parse("x^2 + y^2 + z^2 - 1").unwrap());
+    let mesh = Mesh::new_implicit(&sphere, &min, &max, 20);
+    assert!(is_closed(&mesh));
+    assert!((mesh.volume() - 4.0/3.0*PI).abs() < 0.1);
+
+    // two balls close together melt into one surface, the saddle between them has ambiguous faces
+    let metaballs = ImplicitField::Metaballs(vec![
+        (Vec4::new_point(-0.5, 0.0, 0.0), 0.6),
+        (Vec4::new_point(0.5, 0.1, 0.05), 0.6),
+    ]);
+    let mesh = Mesh::new_implicit(&metaballs, &min, &max, 23);
+    assert!(is_closed(&mesh));
+    assert!(mesh.volume() > 0.0);
+
+    let sdf = ImplicitField::Sdf(vec![
+        SdfPrimitive::Box(Vec4::new_point(0.0, 0.0, -0.5), Vec4::new_vec4(1.0, 1.0, 0.3)),
+        SdfPrimitive::Torus(Vec4::new_point(0.0, 0.0, 0.3), 0.8, 0.2),
+    ], 0.3);
+    let mesh = Mesh::new_implicit(&sdf, &min, &max, 17);
+    assert!(is_closed(&mesh));
+    assert!(mesh.volume() > 0.0);
+}
+
+#[test]
+fn test_teapot() {
+    let teapot = Mesh::new_parametric_surfaces(&crate::teapot::teapot_patches(), 4);
+    assert_eq!(teapot.vertices.len(), 32 * 5*5);
+    assert_eq!(teapot.faces.len(), 32 * 4*4);
+    assert_eq!(teapot.attributes.normals.len(), teapot.vertices.len());
+
+    // the normals agree with the counter clock wise order of the faces, except on a few quads
+    // crossing the fold where the lip of the spout curls over
+    let mut agreeing = 0;
+    let mut checked = 0;
+    for face in teapot.faces.iter() {
+        let [a, b, c, d] = [0, 1, 2, 3].map(|i| teapot.vertices[face.indexes[i]]);
+        let face_normal = c.sub(&a).cross(&d.sub(&b));
+        if face_normal.length() < 1e-6 { continue; }
+        for &i in face.indexes.iter() {
+            checked += 1;
+            if teapot.attributes.normals[i].dot(&face_normal) > 0.0 { agreeing += 1; }
+        }
+    }
+    assert!(agreeing as f32 > 0.99 * checked as f32);
+
+    // the body points away from the axis of the teapot
+    let body_point = teapot.vertices.iter().position(|v| v.z() > 1.0 && v.z() < 1.5 && v.x() > 1.9 && v.y().abs() < 0.5).unwrap();
+    assert!(teapot.attributes.normals[body_point].x() > 0.9);
+}
+
+#[test]
+fn test_convex_hull() {
+    // the corners of a cube with points inside it and on its faces and edges
+    let mut points = Mesh::new_cube().vertices;
+    points.push(Vec4::new_point(0.0, 0.0, 0.0));
+    points.push(Vec4::new_point(0.5, -0.25, 0.1));
+    points.push(Vec4::new_point(1.0, 0.3, -0.2));
+    points.push(Vec4::new_point(0.0, -1.0, 1.0));
+    let cube = Mesh::new_convex_hull(&points);
+    assert_eq!(cube.vertices.len(), 8);
+    assert_eq!(cube.faces.len(), 6);
+    assert!(cube.faces.iter().all(|face| face.indexes.len() == 4));
+    assert!((cube.volume() - 8.0).abs() < 1e-4);
+
+    let rock = Mesh::new_rock(200, 0.3, 5);
+    let volume = rock.volume();
+    assert!(volume > 0.0 && volume < 4.0/3.0*PI);
+    // every face is convex and has every vertex of the rock behind it
+    for face in rock.faces.iter() {
+        let a = rock.vertices[face.indexes[0]];
+        let normal = rock.vertices[face.indexes[1]].sub(&a).cross(&rock.vertices[face.indexes[2]].sub(&a));
+        assert!(rock.vertices.iter().all(|v| normal.dot(&v.sub(&a)) < 1e-4));
+    }
+
+    assert!(Mesh::new_convex_hull(&[Vec4::new_point(0.0, 0.0, 0.0), Vec4::new_point(1.0, 0.0, 0.0),
+                                    Vec4::new_point(0.0, 1.0, 0.0), Vec4::new_point(1.0, 1.0, 0.0)]).faces.is_empty());
+}
+
+#[test]
+fn test_tube_sweeps() {
+    // every point of the torus lies at ring_radius from the center circle
+    let torus = Mesh::new_torus(3.0, 0.5, 12, 8);
+    assert_eq!(torus.faces.len(), 12 * 8);
+    for v in torus.vertices.iter() {
+        let to_ring = (v.x()*v.x() + v.y()*v.y()).sqrt() - 3.0;
+        assert!(((to_ring*to_ring + v.z()*v.z()).sqrt() - 0.5).abs() < 1e-5);
+    }
+    assert!(torus.volume() > 0.0);
+
+    // a knot is closed without caps, all points are tube_radius away from the curve
+    let knot_curve = Curve::TorusKnot(2, 3, 2.0, 0.8);
+    let knot = Mesh::new_tube_sweep(&knot_curve, 0.2, 120, 8);
+    assert_eq!(knot.vertices.len(), 120 * 8);
+    assert_eq!(knot.faces.len(), 120 * 8);
+    for ring_i in 0..120 {
+        let center = knot_curve.point(ring_i as f32 / 120.0);
+        for v in knot.vertices[ring_i * 8..(ring_i + 1) * 8].iter() {
+            assert!((v.sub(&center).length() - 0.2).abs() < 1e-4);
+        }
+    }
+    assert!(knot.volume() > 0.0);
+
+    // the frames of a helix hardly twist, so every side runs along the tube smoothly,
+    // and the ends get caps
+    let helix = Mesh::new_tube_sweep(&Curve::Helix(2.0, 1.0, 3.0), 0.3, 90, 6);
+    assert_eq!(helix.faces.len(), 90 * 6 + 2);
+    for ring_i in 0..90 {
+        let side = helix.vertices[ring_i * 6].sub(&helix.vertices[(ring_i + 1) * 6]).length();
+        let across = helix.vertices[ring_i * 6].sub(&helix.vertices[(ring_i + 1) * 6 + 1]).length();
+        assert!(side < across);
+    }
+    assert!(helix.volume() > 0.0);
+}
+
+#[test]
+fn test_triangulate() {
+    // triangles stay, quads and pentagons are split
+    let mut tetrahedron = Mesh::new_tetrahedron();
+    assert!(tetrahedron.triangulate().is_empty());
+    assert_eq!(tetrahedron.faces.len(), 4);
+
+    let mut dodecahedron = Mesh::new_dodecahedron();
+    let volume = dodecahedron.volume();
+    assert!(dodecahedron.triangulate().is_empty());
+    assert_eq!(dodecahedron.faces.len(), 12 * 3);
+    assert!((dodecahedron.volume() - volume).abs() < 1e-4);
+
+    // a star shaped extrusion has concave caps, a face with two corners can't be triangulated
+    let star: Vec<Vec2> = (0..10).map(|i| {
+        let angle = i as f32 * PI / 5.0;
+        let radius = if i % 2 == 0 { 1.0 } else { 0.4 };
+        Vec2::new(radius * angle.cos(), radius * angle.sin())
+    }).collect();
+    let mut prism = Mesh::new_extrusion(&star, &Vec4::new_vec4(0.0, 0.0, 1.0), 1, 0.0, 1.0);
+    let volume = prism.volume();
+    prism.faces.push(Face::new(vec![0, 1]));
+    // the sides and both caps come before it
+    assert_eq!(prism.triangulate(), vec![star.len() + 2]);
+    assert!(prism.faces.iter().all(|face| face.indexes.len() == 3));
+    assert!((prism.volume() - volume).abs() < 1e-4);
+}
+
+#[test]
+fn test_normals() {
+    // the cube keeps its sharp edges, every face gets its own corners
+    let mut cube = Mesh::new_cube();
+    cube.compute_normals(NormalWeighting::Angle, 60f32.to_radians());
+    assert_eq!(cube.vertices.len(), 24);
+    for face in cube.faces.iter() {
+        let face_normal = cube.face_normal(face).normalize();
+        assert!(face.indexes.iter().all(|&i| cube.attributes.normals[i].dot(&face_normal) > 0.9999));
+    }
+
+    // the torus is smooth, its normals point away from the circle through the middle of the ring
+    let mut torus = Mesh::new_torus(3.0, 1.0, 24, 12);
+    torus.compute_normals(NormalWeighting::Area, 60f32.to_radians());
+    assert_eq!(torus.vertices.len(), 24 * 12);
+    for (v, normal) in torus.vertices.iter().zip(torus.attributes.normals.iter()) {
+        let ring_center = Vec4::new_point(v.x(), v.y(), 0.0).normalize().scale(3.0);
+        let outward = v.sub(&Vec4::new_point(ring_center.x(), ring_center.y(), 0.0)).normalize();
+        assert!(normal.dot(&outward) > 0.99);
+    }
+
+    // in a triangulated cube some corners have two triangles of a face and others one, only angle
+    // weighting is independent of that
+    let mut triangulated = Mesh::new_cube();
+    triangulated.triangulate();
+    let mut by_area = triangulated.clone();
+    triangulated.compute_normals(NormalWeighting::Angle, PI);
+    by_area.compute_normals(NormalWeighting::Area, PI);
+    assert_eq!(triangulated.vertices.len(), 8);
+    for (v, normal) in triangulated.vertices.iter().zip(triangulated.attributes.normals.iter()) {
+        let diagonal = Vec4::new_vec4(v.x(), v.y(), v.z()).normalize();
+        assert!(normal.dot(&diagonal) > 0.9999);
+    }
+    assert!(by_area.vertices.iter().zip(by_area.attributes.normals.iter())
+        .any(|(v, normal)| normal.dot(&Vec4::new_vec4(v.x(), v.y(), v.z()).normalize()) < 0.999));
+}
+
+#[test]
+fn test_subdivision() {
+    // Loop subdivision pulls the icosahedron towards a sphere, inside its old faces
+    let mut sphere = Mesh::new_icosahedron();
+    let volume = sphere.volume();
+    sphere.subdivide_loop(2);
+    assert_eq!(sphere.faces.len(), 20 * 16);
+    assert_eq!(sphere.vertices.len(), 162);
+    let new_volume = sphere.volume();
+    assert!(new_volume > 0.0 && new_volume < volume);
+    let distances: Vec<f32> = sphere.vertices.iter().map(|v| Vec4::new_vec4(v.x(), v.y(), v.z()).length()).collect();
+    let (closest, furthest) = distances.iter().fold((f32::MAX, 0.0f32), |(min, max), &d| (min.min(d), max.max(d)));
+    assert!(furthest / closest < 1.05);
+
+    // a Catmull-Clark cube rounds off, unless all its edges are creases
+    let mut rounded = Mesh::new_cube();
+    rounded.subdivide_catmull_clark(1);
+    assert_eq!(rounded.vertices.len(), 8 + 12 + 6);
+    assert_eq!(rounded.faces.len(), 24);
+    assert!(rounded.volume() < 7.0);
+
+    let mut sharp = Mesh::new_cube();
+    sharp.mark_creases(30f32.to_radians());
+    assert_eq!(sharp.creases.len(), 12);
+    sharp.subdivide_catmull_clark(2);
+    assert_eq!(sharp.creases.len(), 48);
+    assert!((sharp.volume() - 8.0).abs() < 1e-4);
+
+    // the torus quads become smoother but keep their shape, the boundary of a flat grid stays in place
+    let mut torus = Mesh::new_torus(3.0, 1.0, 12, 8);
+    torus.subdivide_catmull_clark(1);
+    assert_eq!(torus.faces.len(), 12 * 8 * 4);
+    assert!(torus.volume() > 0.0);
+
+    let heightmap = Heightmap{width: 4, depth: 4, heights: vec![0.0; 16]};
+    let mut grid = Mesh::new_terrain(&heightmap, 1.0, 1.0);
+    grid.subdivide_catmull_clark(1);
+    assert_eq!(grid.vertices.len(), 7 * 7);
+    assert!(grid.vertices.iter().all(|v| v.z() == 0.0 && v.x().abs() <= 1.5 && v.y().abs() <= 1.5));
+    assert!(grid.vertices.iter().any(|v| v.x() == -1.5 && v.y() == 0.0));
+}
+
+#[test]
+fn test_simplify() {
+    // a finely divided torus keeps its volume and stays closed
+    let mut torus = Mesh::new_torus(3.0, 1.0, 60, 40);
+    let volume = torus.volume();
+    torus.simplify(800);
+    assert!(torus.faces.len() <= 800);
+    assert!((torus.volume() / volume - 1.0).abs() < 0.05);
+    let mut directed_edges = HashMap::new();
+    for face in torus.faces.iter() {
+        for i in 0..3 {
+            *directed_edges.entry((face.indexes[i], face.indexes[(i+1)%3])).or_insert(0) += 1;
+        }
+    }
+    assert!(directed_edges.iter().all(|(&(a, b), &count)| count == 1 && directed_edges.get(&(b, a)) == Some(&1)));
+
+    // a bumpy terrain keeps its square outline and its faces keep facing up
+    let heights = (0..400).map(|i| ((i % 20) as f32 * 0.3).sin() * ((i / 20) as f32 * 0.2).cos()).collect();
+    let mut terrain = Mesh::new_terrain(&Heightmap{width: 20, depth: 20, heights}, 1.0, 1.0);
+    terrain.simplify(150);
+    assert!(terrain.faces.len() <= 150);
+    assert!(terrain.faces.iter().all(|face| terrain.face_normal(face).z() > 0.0));
+    for corner in [(-9.5, -9.5), (9.5, -9.5), (9.5, 9.5), (-9.5, 9.5)] {
+        assert!(terrain.vertices.iter().any(|v| (v.x() - corner.0).abs() < 1e-2 && (v.y() - corner.1).abs() < 1e-2));
+    }
+    let on_outline = |v: &Vec4| (v.x().abs() - 9.5).abs() < 1e-2 || (v.y().abs() - 9.5).abs() < 1e-2;
+    let inside = |v: &Vec4| v.x().abs() < 9.5 + 1e-2 && v.y().abs() < 9.5 + 1e-2;
+    assert!(terrain.vertices.iter().all(inside));
+    assert!(terrain.vertices.iter().filter(|v| on_outline(v)).count() >= 4);
+}
+
+#[test]
+fn test_weld() {
+    // two unit squares next to each other with slightly different copies of their shared corners,
+    // a copy of the first square, a triangle folded onto an edge and a quad with two corners on one vertex
+    let vertices = vec![
+        Vec4::new_point(0.0, 0.0, 0.0), Vec4::new_point(1.0, 0.0, 0.0), Vec4::new_point(1.0, 1.0, 0.0), Vec4::new_point(0.0, 1.0, 0.0),
+        Vec4::new_point(1.00001, 0.0, 0.0), Vec4::new_point(2.0, 0.0, 0.0), Vec4::new_point(2.0, 1.0, 0.0), Vec4::new_point(0.99999, 1.00001, 0.0),
+    ];
+    let faces = vec![
+        Face::new(vec![0, 1, 2, 3]),
+        Face::new(vec![4, 5, 6, 7]),
+        Face::new(vec![2, 3, 0, 1]),
+        Face::new(vec![1, 2, 7]),
+        Face::new(vec![0, 1, 4, 2]),
+    ];
+    let mut mesh = Mesh::new(vertices, faces);
+    mesh.weld(1e-4);
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.faces.len(), 3);
+    assert_eq!(mesh.faces[1].indexes, vec![1, 4, 5, 2]);
+    assert_eq!(mesh.faces[2].indexes, vec![0, 1, 2]);
+
+    // the patches of the teapot share their borders once welded, the lid's tip collapses
+    let mut teapot = Mesh::new_parametric_surfaces(&crate::teapot::teapot_patches(), 4);
+    let vertices_amt = teapot.vertices.len();
+    teapot.weld(1e-4);
+    assert!(teapot.vertices.len() < vertices_amt * 3 / 4);
+    assert_eq!(teapot.attributes.normals.len(), teapot.vertices.len());
+    assert!(teapot.faces.iter().any(|face| face.indexes.len() == 3));
+}
+
+#[test]
+fn test_validate_and_repair() {
+    let report = Mesh::new_cube().validate();
+    assert!(report.is_valid());
+    assert_eq!(report.euler_characteristic, 2);
+    assert_eq!(Mesh::new_torus(3.0, 1.0, 8, 6).validate().euler_characteristic, 0);
+
+    // a cube without its top, one face turned over, an extra face on an edge, a face pointing
+    // past the vertices, a face without area and a vertex of its own
+    let mut broken = Mesh::new_cube();
+    broken.faces.pop();
+    broken.faces[0].indexes.reverse();
+    broken.vertices.push(Vec4::new_point(5.0, 5.0, 5.0));
+    broken.vertices.push(Vec4::new_point(0.0, 0.0, 3.0));
+    broken.faces.push(Face::new(vec![0, 1, 9]));
+    broken.faces.push(Face::new(vec![0, 1, 20]));
+    broken.faces.push(Face::new(vec![2, 3, 2]));
+    let report = broken.validate();
+    assert!(!report.is_valid());
+    assert_eq!(report.out_of_range_faces, vec![6]);
+    assert_eq!(report.degenerate_faces, vec![7]);
+    assert_eq!(report.non_manifold_edges, vec![(0, 1)]);
+    assert_eq!(report.inconsistent_edges.len(), 3);
+    assert_eq!(report.isolated_vertices, vec![8]);
+    assert!(!report.holes.is_empty());
+    assert_eq!(report.problems().len(), 6);
+
+    // without the extras the repaired box is closed and points outward again, a face with a
+    // corner repeated in a row gets it joined
+    broken.faces.truncate(5);
+    let corners = broken.faces[1].indexes.clone();
+    broken.faces[1] = Face::new(vec![corners[0], corners[1], corners[1], corners[2], corners[3]]);
+    broken.repair(4);
+    let report = broken.validate();
+    assert!(report.holes.is_empty() && report.inconsistent_edges.is_empty());
+    assert!((broken.volume() - 8.0).abs() < 1e-5);
+
+    // the same box turned inside out is turned back, bigger holes stay open
+    let mut inside_out = Mesh::new_cube();
+    inside_out.faces.iter_mut().for_each(|face| face.indexes.reverse());
+    inside_out.repair(4);
+    assert!((inside_out.volume() - 8.0).abs() < 1e-5);
+    let mut open = Mesh::new_cube();
+    open.faces.pop();
+    open.repair(3);
+    assert_eq!(open.validate().holes, vec![vec![4, 5, 7, 6]]);
+}
+
+#[test]
+fn test_csg() {
+    // two cubes of volume 8 overlapping in a 1 by 2 by 2 slab
+    let a = Mesh::new_cube();
+    let mut b = Mesh::new_cube();
+    b.transform(&Matrix4::new_translation(&Vec4::new_vec4(1.0, 0.0, 0.0)));
+
+    for (result, volume) in [(a.union(&b), 12.0), (a.intersection(&b), 4.0), (a.difference(&b), 4.0)] {
+        assert!((result.volume() - volume).abs() < 1e-3);
+        assert!(result.validate().non_manifold_edges.is_empty());
+    }
+
+    // a plate with a hole drilled through it keeps its outside and gains the inside of the hole
+    let plate = Mesh::new_extrusion(
+        &[Vec2::new(-2.0, -2.0), Vec2::new(2.0, -2.0), Vec2::new(2.0, 2.0), Vec2::new(-2.0, 2.0)],
+        &Vec4::new_vec4(0.0, 0.0, 0.5), 1, 0.0, 1.0);
+    let mut drill = Mesh::new_lathe(&[Vec2::new(0.0, -1.0), Vec2::new(0.5, -1.0), Vec2::new(0.5, 1.0), Vec2::new(0.0, 1.0)], 16);
+    drill.transform(&Matrix4::new_rotation_z(0.1));
+    let drilled = plate.difference(&drill);
+    let hole_volume = 0.5 * 16.0 / 2.0 * 0.5 * 0.5 * (2.0 * PI / 16.0).sin();
+    assert!((drilled.volume() - (8.0 - hole_volume)).abs() < 1e-3);
+    assert!(drilled.vertices.iter().any(|v| (v.x()*v.x() + v.y()*v.y()).sqrt() < 0.51));
+}
+
+#[test]
+fn test_lathe_and_extrusion() {
+    // a cylinder closed at the bottom: the first profile point lies on the axis
+    let profile = vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0)];
+    let lathe = Mesh::new_lathe(&profile, 12);
+    assert_eq!(lathe.vertices.len(), 1 + 2*12);
+    assert_eq!(lathe.faces.len(), 2*12);
+    assert!(lathe.faces[..12].iter().all(|face| face.indexes.len() == 3));
+
+    let square = vec![Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0), Vec2::new(-1.0, 1.0)];
+    let extrusion = Mesh::new_extrusion(&square, &Vec4::new_vec4(0.0, 0.0, 4.0), 4, PI/2.0, 0.5);
+    assert_eq!(extrusion.vertices.len(), 5*4);
+    assert_eq!(extrusion.faces.len(), 4*4 + 2);
+
+    // the last layer is rotated a quarter turn and halved
+    let last = extrusion.vertices[4*4];
+    assert!((last.x() - 0.5).abs() < 1e-5 && (last.y() + 0.5).abs() < 1e-5 && (last.z() - 4.0).abs() < 1e-5);
+
+    // too few steps are raised to the fewest that still make a solid
+    let flat = Mesh::new_extrusion(&square, &Vec4::new_vec4(0.0, 0.0, 4.0), 0, 0.0, 1.0);
+    assert!(flat.vertices.iter().all(|v| v.z().is_finite()));
+    assert_eq!(flat.faces.len(), 4 + 2);
+    assert_eq!(Mesh::new_lathe(&profile, 0).faces.len(), 2*3);
+}
+
+#[test]
+fn test_smoothing() {
+    use crate::heightmap::Heightmap;
+
+    // a noisy sphere
+    let mut sphere = Mesh::new_icosahedron();
+    sphere.subdivide_loop(3);
+    let mut random = Random::new(7);
+    for vertex in sphere.vertices.iter_mut() {
+        let radius = 1.0 + random.range_f32(-0.05, 0.05);
+        *vertex = Vec4::new_point(vertex.x(), vertex.y(), vertex.z()).normalize().scale(radius);
+    }
+    let noise = |mesh: &Mesh| {
+        let radii: Vec<f32> = mesh.vertices.iter().map(|v| v.length()).collect();
+        let average = radii.iter().sum::<f32>() / radii.len() as f32;
+        (radii.iter().map(|r| (r - average) * (r - average)).sum::<f32>() / radii.len() as f32).sqrt() / average
+    };
+    let volume = sphere.volume();
+
+    let mut laplacian = sphere.clone();
+    laplacian.smooth_laplacian(20, 0.5, false).unwrap();
+    let mut taubin = sphere.clone();
+    taubin.smooth_taubin(20, 0.5, -0.53, false).unwrap();
+    assert!(noise(&laplacian) < noise(&sphere) / 3.0);
+    assert!(noise(&taubin) < noise(&sphere) / 3.0);
+    // Laplacian smoothing shrinks the sphere, Taubin's keeps its volume
+    assert!(laplacian.volume() < volume * 0.9);
+    assert!((taubin.volume() - volume).abs() < volume * 0.03);
+
+    // a bumpy terrain flattens out, pinning keeps its border in place
+    let heights = (0..100).map(|i| if (i / 10 + i % 10) % 2 == 0 { 1.0 } else { 0.0 }).collect();
+    let terrain = Mesh::new_terrain(&Heightmap{width: 10, depth: 10, heights}, 1.0, 1.0);
+    let mut pinned = terrain.clone();
+    pinned.smooth_laplacian(10, 0.5, true).unwrap();
+    let border: Vec<usize> = (0..100).filter(|i| i / 10 == 0 || i / 10 == 9 || i % 10 == 0 || i % 10 == 9).collect();
+    assert!(border.iter().all(|&i| pinned.vertices[i] == terrain.vertices[i]));
+    assert!(pinned.vertices[55].z() > 0.3 && pinned.vertices[55].z() < 0.7);
+    let mut free = terrain.clone();
+    free.smooth_laplacian(10, 0.5, false).unwrap();
+    assert!(free.vertices[0] != terrain.vertices[0]);
+    assert!(free.vertices[1].z() > 0.3 && free.vertices[1].z() < 0.7);
+
+    // three faces on one edge can't be made into half edges, so the mesh isn't smoothed
+    let mut fan = Mesh::new(
+        vec![Vec4::new_point(0.0, 0.0, 0.0), Vec4::new_point(1.0, 0.0, 0.0), Vec4::new_point(0.0, 1.0, 0.0),
+             Vec4::new_point(0.0, -1.0, 0.0), Vec4::new_point(0.0, 0.0, 1.0)],
+        vec![Face::new(vec![0, 1, 2]), Face::new(vec![1, 0, 3]), Face::new(vec![0, 1, 4])],
+    );
+    assert!(fan.smooth_laplacian(3, 0.5, false).is_err());
+    assert_eq!(fan.vertices[4], Vec4::new_point(0.0, 0.0, 1.0));
+}
+
+#[test]
+fn test_slice() {
+    let area = |polyline: &Polyline| {
+        let n = polyline.points.len();
+        (0..n).map(|i| {
+            let (p, q) = (polyline.points[i], polyline.points[(i+1)%n]);
+            p.x() * q.y() - q.x() * p.y()
+        }).sum::<f32>() / 2.0
+    };
+    let up = Vec4::new_vec4(0.0, 0.0, 1.0);
+
+    // through the middle of the cube a square of 2 by 2, going counter clock wise
+    let loops = Mesh::new_cube().slice(&Plane::new(&Vec4::new_point(0.0, 0.0, 0.3), &up));
+    assert_eq!(loops.len(), 1);
+    assert!(loops[0].closed);
+    assert!((area(&loops[0]) - 4.0).abs() < 1e-5);
+    assert!(loops[0].points.iter().all(|p| (p.z() - 0.3).abs() < 1e-6));
+    assert!(Mesh::new_cube().slice(&Plane::new(&Vec4::new_point(0.0, 0.0, 3.0), &up)).is_empty());
+
+    // a torus lying flat is cut in two loops around its hole, the inner one running the other way
+    let torus = Mesh::new_torus(3.0, 1.0, 36, 36);
+    let mut loops = torus.slice(&Plane::new(&Vec4::new_point(0.0, 0.0, 0.0), &up));
+    loops.sort_by(|a, b| area(b).abs().total_cmp(&area(a).abs()));
+    assert_eq!(loops.len(), 2);
+    assert!(loops.iter().all(|polyline| polyline.closed));
+    assert!((area(&loops[0]) - 16.0 * PI).abs() < 0.5 && (area(&loops[1]) + 4.0 * PI).abs() < 0.2);
+    // standing up it is cut in two separate circles
+    let loops = torus.slice(&Plane::new(&Vec4::new_point(0.0, 0.0, 0.0), &Vec4::new_vec4(1.0, 0.0, 0.0)));
+    assert_eq!(loops.len(), 2);
+
+    // an open surface gives open polylines
+    let mut open_cube = Mesh::new_cube();
+    open_cube.faces.pop();
+    let cuts = open_cube.slice(&Plane::new(&Vec4::new_point(0.3, 0.3, 0.3), &Vec4::new_vec4(1.0, 1.0, 1.0)));
+    assert_eq!(cuts.len(), 1);
+    assert!(!cuts[0].closed);
+}
+
+#[test]
+fn test_uvs() {
+    let close = |uv: &Vec2, u: f32, v: f32| (uv.x() - u).abs() < 1e-5 && (uv.y() - v).abs() < 1e-5;
+
+    // a quarter of the way around the torus, at the top of the ring
+    let torus = Mesh::new_torus(3.0, 1.0, 8, 4);
+    assert_eq!(torus.attributes.uvs.len(), torus.vertices.len());
+    assert!(close(&torus.attributes.uvs[2 * 4 + 1], 0.25, 0.25));
+    let mut split = torus.clone();
+    split.compute_normals(NormalWeighting::Angle, 10f32.to_radians());
+    assert_eq!(split.attributes.uvs.len(), split.vertices.len());
+    // split vertices keep the coordinates of the vertex they were split from
+    assert!((0..split.vertices.len()).all(|i| {
+        let original = torus.vertices.iter().position(|v| *v == split.vertices[i]).unwrap();
+        split.attributes.uvs[i] == torus.attributes.uvs[original]
+    }));
+
+    // planar along z is the x and y of the box
+    let mut cube = Mesh::new_cube();
+    cube.project_uvs(UvProjection::Planar(Vec4::new_vec4(0.0, 0.0, 1.0)));
+    assert!(cube.vertices.iter().zip(cube.attributes.uvs.iter()).all(|(p, uv)| close(uv, (p.x() + 1.0) / 2.0, (p.y() + 1.0) / 2.0)));
+
+    let mut sphere = Mesh::new_icosahedron();
+    sphere.project_uvs(UvProjection::Spherical);
+    let top = (0..sphere.vertices.len()).max_by(|&a, &b| sphere.vertices[a].z().total_cmp(&sphere.vertices[b].z())).unwrap();
+    assert!(sphere.attributes.uvs[top].y() > 0.99);
+    assert!(sphere.attributes.uvs.iter().all(|uv| (0.0..=1.0).contains(&uv.x()) && (0.0..=1.0).contains(&uv.y())));
+
+    let mut tube = Mesh::new_lathe(&[Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0)], 4);
+    let analytic = tube.attributes.uvs.clone();
+    tube.project_uvs(UvProjection::Cylindrical);
+    // the point on +y is a quarter turn from +x, so both agree up to where the angle starts
+    assert!(close(&analytic[1], 0.25, 0.0) && close(&tube.attributes.uvs[1], 0.75, 0.0) && close(&tube.attributes.uvs[5], 0.75, 1.0));
+
+    // with split corners every face of the cube gets a whole square of the texture
+    let mut boxed = Mesh::new_cube();
+    boxed.compute_normals(NormalWeighting::Angle, 60f32.to_radians());
+    boxed.project_uvs(UvProjection::Box);
+    for face in boxed.faces.iter() {
+        let mut uvs: Vec<(i32, i32)> = face.indexes.iter().map(|&i| (boxed.attributes.uvs[i].x().round() as i32, boxed.attributes.uvs[i].y().round() as i32)).collect();
+        uvs.sort();
+        assert_eq!(uvs, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+    }
+}
+
+#[test]
+fn test_attributes_through_operations() {
+    use crate::heightmap::Heightmap;
+
+    let consistent = |mesh: &Mesh| {
+        let n = mesh.vertices.len();
+        mesh.attributes.colors.len() == n && mesh.attributes.uvs.len() == n && mesh.attributes.scalars["height"].len() == n
+    };
+
+    // the color of every vertex follows its height
+    let heights = (0..25).map(|i| ((i % 5) * (i / 5)) as f32).collect();
+    let mut terrain = Mesh::new_terrain(&Heightmap{width: 5, depth: 5, heights}, 1.0, 1.0);
+    terrain.color_by_scalar("height", &Color::new(0.0, 0.0, 0.0), &Color::new(1.0, 1.0, 1.0)).unwrap();
+    assert!(consistent(&terrain));
+    let color_follows_height = |mesh: &Mesh| mesh.vertices.iter().zip(mesh.attributes.colors.iter())
+        .all(|(v, c)| (c.r() - v.z() / 16.0).abs() < 1e-4);
+    assert!(color_follows_height(&terrain));
+
+    let mut subdivided = terrain.clone();
+    subdivided.subdivide_catmull_clark(1);
+    assert!(consistent(&subdivided));
+    // the new points blend the vertices around them
+    let face_point = subdivided.vertices.len() - 1;
+    assert!((subdivided.attributes.scalars["height"][face_point] - (9.0 + 12.0 + 16.0 + 12.0) / 4.0).abs() < 1e-5);
+
+    let mut split = terrain.clone();
+    split.compute_normals(NormalWeighting::Angle, 0.0);
+    split.triangulate();
+    split.weld(1e-4);
+    assert!(consistent(&split) && color_follows_height(&split));
+    // collapsed vertices move, they keep the attributes of one of the ends
+    split.simplify(10);
+    assert!(consistent(&split));
+    assert!(split.attributes.normals.is_empty() && !split.attributes.colors.is_empty());
+
+    // cutting through the colored cube blends the colors along the cut
+    let mut cube = Mesh::new_cube();
+    cube.attributes.colors = cube.vertices.iter().map(|v| Color::new((v.x() + 1.0) / 2.0, 0.0, 0.0)).collect();
+    let mut cutter = Mesh::new_cube();
+    cutter.transform(&Matrix4::new_translation(&Vec4::new_vec4(1.5, 1.0, 1.0)));
+    cutter.attributes.colors = vec![Color::new(0.0, 0.0, 1.0); 8];
+    let cut = cube.difference(&cutter);
+    assert_eq!(cut.attributes.colors.len(), cut.vertices.len());
+    assert!(cut.vertices.iter().zip(cut.attributes.colors.iter())
+        .filter(|(v, _)| v.x() < 0.49)
+        .all(|(v, c)| (c.r() - (v.x() + 1.0) / 2.0).abs() < 1e-4));
+
+    // tangents turn with the mesh
+    let mut turned = Mesh::new_cube();
+    turned.attributes.tangents = vec![Vec4::new_vec4(1.0, 0.0, 0.0); 8];
+    turned.transform(&Matrix4::new_rotation_z(-PI / 2.0));
+    assert!(turned.attributes.tangents.iter().all(|t| (t.y() - 1.0).abs() < 1e-5));
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use crate::figure::Mesh;
+
+/// One side of an edge, running counter clock wise around its face. Holes in the surface are
+/// surrounded by half edges without a face, so every half edge has a twin.
+#[derive(Clone, Copy, Debug)]
+pub struct HalfEdge {
+    pub vertex: usize, // the vertex it points to
+    pub face: Option<usize>, // None along a boundary
+    pub next: usize,
+    pub previous: usize,
+    pub twin: usize,
+}
+
+/// A mesh as half edges, for walking from a vertex or face to its neighbours in constant time
+pub struct HalfEdgeMesh {
+    pub half_edges: Vec<HalfEdge>,
+    pub vertex_half_edges: Vec<Option<usize>>, // one half edge leaving every vertex, the boundary one if there is one
+}
+
+impl HalfEdgeMesh {
+    /// Fails when an edge has more than two faces, two faces run along an edge in the same direction,
+    /// or a vertex is shared by surfaces that only touch in that vertex
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, String> {
+        let mut half_edges: Vec<HalfEdge> = Vec::new();
+        let mut by_ends: HashMap<(usize, usize), usize> = HashMap::new();
+
+        for (face_i, face) in mesh.faces.iter().enumerate() {
+            let n = face.indexes.len();
+            if n < 3 {
+                return Err(format!("face {face_i} has less than three corners"));
+            }
+            let first = half_edges.len();
+            for i in 0..n {
+                let (from, to) = (face.indexes[i], face.indexes[(i+1)%n]);
+                if from >= mesh.vertices.len() || to >= mesh.vertices.len() {
+                    return Err(format!("face {face_i} points to a vertex that doesn't exist"));
+                }
+                if by_ends.insert((from, to), first + i).is_some() {
+                    return Err(format!("edge ({from}, {to}) is used twice in the same direction"));
+                }
+                half_edges.push(HalfEdge{
+                    vertex: to,
+                    face: Some(face_i),
+                    next: first + (i+1)%n,
+                    previous: first + (i+n-1)%n,
+                    twin: usize::MAX,
+                });
+            }
+        }
+
+        // twins, and half edges around the holes where a face has no neighbour
+        let mut boundary_leaving: HashMap<usize, usize> = HashMap::new();
+        let faces_half_edges_amt = half_edges.len();
+        for h in 0..faces_half_edges_amt {
+            if half_edges[h].twin != usize::MAX { continue; }
+            let to = half_edges[h].vertex;
+            let from = half_edges[half_edges[h].previous].vertex;
+            match by_ends.get(&(to, from)) {
+                Some(&twin) => {
+                    half_edges[h].twin = twin;
+                    half_edges[twin].twin = h;
+                }
+                None => {
+                    let boundary = half_edges.len();
+                    half_edges[h].twin = boundary;
+                    half_edges.push(HalfEdge{vertex: from, face: None, next: usize::MAX, previous: usize::MAX, twin: h});
+                    if boundary_leaving.insert(to, boundary).is_some() {
+                        return Err(format!("vertex {to} lies on two boundaries"));
+                    }
+                }
+            }
+        }
+
+        // a boundary half edge continues with the boundary half edge leaving the vertex it points to
+        for h in faces_half_edges_amt..half_edges.len() {
+            let next = boundary_leaving[&half_edges[h].vertex];
+            half_edges[h].next = next;
+            half_edges[next].previous = h;
+        }
+
+        let mut vertex_half_edges = vec![None; mesh.vertices.len()];
+        for (h, half_edge) in half_edges.iter().enumerate() {
+            let from = half_edges[half_edge.previous].vertex;
+            if vertex_half_edges[from].is_none() || half_edge.face.is_none() {
+                vertex_half_edges[from] = Some(h);
+            }
+        }
+
+        let half_edge_mesh = Self{half_edges, vertex_half_edges};
+
+        // walking around every vertex has to visit all of its half edges
+        let mut leaving_amt = vec![0; mesh.vertices.len()];
+        for half_edge in half_edge_mesh.half_edges.iter() {
+            leaving_amt[half_edge_mesh.half_edges[half_edge.twin].vertex] += 1;
+        }
+        for (v, &amt) in leaving_amt.iter().enumerate() {
+            if half_edge_mesh.leaving(v).count() != amt {
+                return Err(format!("the surfaces around vertex {v} only touch in that vertex"));
+            }
+        }
+
+        Ok(half_edge_mesh)
+    }
+
+    /// The vertex a half edge starts from
+    pub fn origin(&self, h: usize) -> usize {
+        self.half_edges[self.half_edges[h].twin].vertex
+    }
+
+    /// The half edges leaving the vertex, counter clock wise seen from outside
+    pub fn leaving(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.vertex_half_edges[v];
+        let mut current = start;
+        std::iter::from_fn(move || {
+            let h = current?;
+            let next = self.half_edges[self.half_edges[h].previous].twin;
+            current = if Some(next) == start { None } else { Some(next) };
+            Some(h)
+        })
+    }
+
+    /// The neighbours of the vertex
+    pub fn one_ring(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.leaving(v).map(|h| self.half_edges[h].vertex)
+    }
+
+    pub fn is_boundary_vertex(&self, v: usize) -> bool {
+        self.vertex_half_edges[v].is_some_and(|h| self.half_edges[h].face.is_none())
+    }
+
+    /// The vertices around every hole, in the order of the boundary half edges
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut loops = Vec::new();
+        for h in 0..self.half_edges.len() {
+            if visited[h] || self.half_edges[h].face.is_some() { continue; }
+            let boundary: Vec<usize> = self.cycle(h).collect();
+            for &b in boundary.iter() { visited[b] = true; }
+            loops.push(boundary.iter().map(|&b| self.origin(b)).collect());
+        }
+        loops
+    }
+
+    /// Follows `next` from the half edge until it comes back
+    fn cycle(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut current = Some(start);
+        std::iter::from_fn(move || {
+            let h = current?;
+            let next = self.half_edges[h].next;
+            current = if next == start { None } else { Some(next) };
+            Some(h)
+        })
+    }
+}
+
+#[test]
+fn test_half_edges() {
+    use crate::figure::Face;
+    use crate::heightmap::Heightmap;
+    use crate::vec4::Vec4;
+
+    let cube = HalfEdgeMesh::from_mesh(&Mesh::new_cube()).unwrap();
+    assert_eq!(cube.half_edges.len(), 24);
+    assert!(cube.boundary_loops().is_empty());
+    for v in 0..8 {
+        let mut ring: Vec<usize> = cube.one_ring(v).collect();
+        ring.sort();
+        // the neighbours of a corner differ from it in one coordinate
+        assert_eq!(ring, {
+            let mut expected = vec![v ^ 1, v ^ 2, v ^ 4];
+            expected.sort();
+            expected
+        });
+    }
+
+    // a 4 by 4 grid of points has one hole around its outside
+    let grid = HalfEdgeMesh::from_mesh(&Mesh::new_terrain(&Heightmap{width: 4, depth: 4, heights: vec![0.0; 16]}, 1.0, 1.0)).unwrap();
+    let loops = grid.boundary_loops();
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].len(), 12);
+    assert!(grid.is_boundary_vertex(0) && !grid.is_boundary_vertex(5));
+    assert_eq!(grid.one_ring(0).count(), 2);
+    assert_eq!(grid.one_ring(5).count(), 4);
+    let along_boundary = grid.leaving(0).find(|&h| grid.half_edges[h].face.is_none()).unwrap();
+    assert!(grid.half_edges[grid.half_edges[along_boundary].twin].face.is_some());
+
+    // three faces on one edge
+    let fan = Mesh::new(
+        vec![Vec4::new_point(0.0, 0.0, 0.0), Vec4::new_point(1.0, 0.0, 0.0), Vec4::new_point(0.0, 1.0, 0.0),
+             Vec4::new_point(0.0, -1.0, 0.0), Vec4::new_point(0.0, 0.0, 1.0)],
+        vec![Face::new(vec![0, 1, 2]), Face::new(vec![1, 0, 3]), Face::new(vec![0, 1, 4])],
+    );
+    assert!(HalfEdgeMesh::from_mesh(&fan).is_err());
+}
@@ -0,0 +1,46 @@
+use crate::noise::PerlinNoise;
+
+/// A grid of heights, `width` points along x and `depth` points along y
+pub struct Heightmap {
+    pub width: usize,
+    pub depth: usize,
+    pub heights: Vec<f32>, // row by row, heights[y * width + x]
+}
+
+impl Heightmap {
+    /// Every pixel becomes a grid point, its brightness in [0, 1] the height
+    pub fn from_bmp(path: &str) -> Self {
+        let image = bmp::open(path).unwrap_or_else(|_| panic!("Failed to open heightmap: {}", path));
+        let (width, depth) = (image.get_width() as usize, image.get_height() as usize);
+
+        let mut heights = Vec::with_capacity(width * depth);
+        for y in 0..depth {
+            for x in 0..width {
+                // bmp rows go from top to bottom, our y axis goes up
+                let pixel = image.get_pixel(x as u32, (depth - 1 - y) as u32);
+                heights.push((pixel.r as f32 + pixel.g as f32 + pixel.b as f32) / (3.0 * 255.0));
+            }
+        }
+
+        Self{width, depth, heights}
+    }
+
+    /// Heights from fractal noise sampled every `1/frequency` noise units
+    pub fn from_noise(width: usize, depth: usize, seed: u64, frequency: f32,
+                      octaves: u32, lacunarity: f32, gain: f32) -> Self {
+        let noise = PerlinNoise::new(seed);
+
+        let mut heights = Vec::with_capacity(width * depth);
+        for y in 0..depth {
+            for x in 0..width {
+                heights.push(noise.fbm(x as f32 * frequency, y as f32 * frequency, octaves, lacunarity, gain));
+            }
+        }
+
+        Self{width, depth, heights}
+    }
+
+    pub fn height(&self, x: usize, y: usize) -> f32 {
+        self.heights[y * self.width + x]
+    }
+}
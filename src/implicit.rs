@@ -0,0 +1,89 @@
+use crate::expression::Expression;
+use crate::vec4::Vec4;
+
+/// Signed distance primitives
+pub enum SdfPrimitive {
+    Sphere(Vec4, f32), // center, radius
+    Box(Vec4, Vec4), // center, half the size along every axis
+    Torus(Vec4, f32, f32), // center, radius, ring_radius; lies in the xy plane
+}
+
+/// A scalar field that is negative inside the surface and positive outside of it
+pub enum ImplicitField {
+    Metaballs(Vec<(Vec4, f32)>), // (center, radius) of every ball
+    Sdf(Vec<SdfPrimitive>, f32), // primitives, smoothness of their union
+    Expression(Expression),
+}
+
+impl SdfPrimitive {
+    pub fn distance(&self, p: &Vec4) -> f32 {
+        match self {
+            SdfPrimitive::Sphere(center, radius) => p.sub(center).length() - radius,
+            SdfPrimitive::Box(center, half_size) => {
+                let d = p.sub(center);
+                let q = [d.x().abs() - half_size.x(), d.y().abs() - half_size.y(), d.z().abs() - half_size.z()];
+                let outside = Vec4::new_vec4(q[0].max(0.0), q[1].max(0.0), q[2].max(0.0)).length();
+                let inside = q[0].max(q[1]).max(q[2]).min(0.0);
+                outside + inside
+            }
+            SdfPrimitive::Torus(center, radius, ring_radius) => {
+                let d = p.sub(center);
+                let to_ring = (d.x()*d.x() + d.y()*d.y()).sqrt() - radius;
+                (to_ring*to_ring + d.z()*d.z()).sqrt() - ring_radius
+            }
+        }
+    }
+}
+
+impl ImplicitField {
+    pub fn value(&self, p: &Vec4) -> f32 {
+        match self {
+            ImplicitField::Metaballs(balls) => {
+                // every ball contributes (r/d)^2, the surface is where the contributions add up to 1
+                let sum: f32 = balls.iter()
+                    .map(|(center, radius)| {
+                        let d = p.sub(center);
+                        radius*radius / d.dot(&d).max(f32::EPSILON)
+                    })
+                    .sum();
+                1.0 - sum
+            }
+            ImplicitField::Sdf(primitives, smoothness) => {
+                primitives.iter()
+                    .map(|primitive| primitive.distance(p))
+                    .reduce(|a, b| smooth_min(a, b, *smoothness))
+                    .unwrap_or(f32::INFINITY)
+            }
+            ImplicitField::Expression(expression) => expression.evaluate(p.x(), p.y(), p.z()),
+        }
+    }
+}
+
+/// Polynomial smooth minimum, blends a and b over a distance of about `smoothness`
+pub fn smooth_min(a: f32, b: f32, smoothness: f32) -> f32 {
+    if smoothness <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0.0, 1.0);
+    b + (a - b) * h - smoothness * h * (1.0 - h)
+}
+
+#[test]
+fn test_implicit_fields() {
+    let origin = Vec4::new_point(0.0, 0.0, 0.0);
+
+    let sphere = SdfPrimitive::Sphere(origin, 2.0);
+    assert_eq!(sphere.distance(&Vec4::new_point(3.0, 0.0, 0.0)), 1.0);
+    let cube = SdfPrimitive::Box(origin, Vec4::new_vec4(1.0, 1.0, 1.0));
+    assert_eq!(cube.distance(&Vec4::new_point(0.5, 0.0, 0.0)), -0.5);
+    let torus = SdfPrimitive::Torus(origin, 3.0, 1.0);
+    assert_eq!(torus.distance(&Vec4::new_point(3.0, 0.0, 0.0)), -1.0);
+
+    // smoothing only makes the union bigger
+    assert!(smooth_min(1.0, 1.0, 0.5) < 1.0);
+    assert_eq!(smooth_min(1.0, 3.0, 0.5), 1.0);
+
+    let metaball = ImplicitField::Metaballs(vec![(origin, 1.0)]);
+    assert!(metaball.value(&Vec4::new_point(0.5, 0.0, 0.0)) < 0.0);
+    assert!(metaball.value(&Vec4::new_point(1.5, 0.0, 0.0)) > 0.0);
+}
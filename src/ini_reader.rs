@@ -131,6 +131,17 @@ impl Section {
     pub fn key_exists(&self, key: &str) -> bool {
         self.values.contains_key(key)
     }
+
+    /// All keys and values except the ignored keys, sorted by key, so sections with the same
+    /// values get the same fingerprint
+    pub fn fingerprint(&self, ignored_keys: &[&str]) -> String {
+        let mut entries: Vec<String> = self.values.iter()
+            .filter(|(key, _)| !ignored_keys.contains(&key.as_str()))
+            .map(|(key, value)| format!("{key} = {value:?}"))
+            .collect();
+        entries.sort();
+        entries.join("\n")
+    }
 }
 
 pub struct IniConfiguration {
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::vec4::Vec4;
+use crate::vec2::Vec2;
+use crate::random::Random;
+
+/// An L-system as read from a `.L2D` or `.L3D` file:
+///
+/// ```text
+/// Alphabet = {F, X}
+/// Draw = {
+///     F -> 1
+///     X -> 0
+/// }
+/// Rules = {
+///     F -> "FF"
+///     X -> "F[+X]F[-X]+X"
+/// }
+/// Initiator = "X"
+/// Angle = 25
+/// Iterations = 4
+/// ```
+///
+/// Letters that draw move the turtle forward leaving a line, letters that don't draw
+/// only move it. Letters without a rule are copied unchanged when the string is replaced.
+///
+/// A letter with several rules is stochastic, every rule is then preceded by its probability
+/// (`F -> 0.4 "F[+F]F"`) and one of them is picked at random for every replaced letter.
+/// The optional `Seed` key makes another random choice, `StartingAngle` sets the direction
+/// the 2D turtle starts in.
+pub struct LSystem {
+    pub alphabet: Vec<char>,
+    pub draw: HashMap<char, bool>,
+    pub rules: HashMap<char, Vec<(f32, String)>>, // (probability, replacement)
+    pub initiator: String,
+    pub angle_rad: f32,
+    pub starting_angle_rad: f32,
+    pub iterations: u32,
+    pub seed: u64,
+}
+
+/// A line drawn by the 3D turtle together with the turtle's left and up direction,
+/// handy to put a cross section around the line.
+pub struct TurtleSegment {
+    pub start: Vec4,
+    pub end: Vec4,
+    pub left: Vec4,
+    pub up: Vec4,
+}
+
+impl LSystem {
+    pub fn from_file(path: &str) -> Self {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|_| panic!("Failed to open L-system file: {}", path));
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let entries = parse_entries(text);
+
+        let get = |key: &str| -> &str {
+            entries.get(key).unwrap_or_else(|| panic!("L-system has no {key}"))
+        };
+
+        let alphabet: Vec<char> = split_block(get("Alphabet"))
+            .iter()
+            .map(|letter| single_char(letter))
+            .collect();
+
+        let mut draw = HashMap::new();
+        for (letter, value) in split_block(get("Draw")).iter().map(|entry| split_rule(entry)) {
+            draw.insert(letter, value.trim() == "1");
+        }
+
+        let mut rules: HashMap<char, Vec<(f32, String)>> = HashMap::new();
+        if let Some(block) = entries.get("Rules") {
+            for (letter, value) in split_block(block).iter().map(|entry| split_rule(entry)) {
+                let rule = match value.split_once('"') {
+                    Some((probability, _)) if !probability.trim().is_empty() => {
+                        let probability = probability.trim().parse::<f32>()
+                            .unwrap_or_else(|_| panic!("L-system rule probability must be a number, got: {probability}"));
+                        (probability, unquote(&value[value.find('"').unwrap()..]))
+                    }
+                    _ => (1.0, unquote(value)),
+                };
+                rules.entry(letter).or_default().push(rule);
+            }
+        }
+
+        for letter in alphabet.iter() {
+            if !draw.contains_key(letter) {
+                eprintln!("L-system letter {letter} has no draw flag, it won't be drawn");
+            }
+        }
+
+        let initiator = unquote(get("Initiator"));
+        let angle_rad = get("Angle").trim().parse::<f32>().expect("Angle must be a number").to_radians();
+        let iterations = get("Iterations").trim().parse::<u32>().expect("Iterations must be a whole number");
+        let starting_angle_rad = entries.get("StartingAngle")
+            .map(|angle| angle.trim().parse::<f32>().expect("StartingAngle must be a number").to_radians())
+            .unwrap_or(0.0);
+        let seed = entries.get("Seed")
+            .map(|seed| seed.trim().parse::<u64>().expect("Seed must be a whole number"))
+            .unwrap_or(0);
+
+        Self{alphabet, draw, rules, initiator, angle_rad, starting_angle_rad, iterations, seed}
+    }
+
+    /// Applies the replacement rules `iterations` times to the initiator
+    pub fn expand(&self) -> String {
+        let mut random = Random::new(self.seed);
+
+        let mut current = self.initiator.clone();
+        for _ in 0..self.iterations {
+            let mut next = String::with_capacity(current.len() * 2);
+            for c in current.chars() {
+                match self.rules.get(&c) {
+                    Some(rules) => next.push_str(choose_rule(rules, &mut random)),
+                    None => next.push(c),
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Walks the expanded string with a 2D turtle that starts at the origin heading in the
+    /// starting angle. `+`/`-` turn counter clock wise/clock wise, `[`/`(` push and `]`/`)`
+    /// pop the turtle state.
+    pub fn interpret_2d(&self) -> Vec<(Vec2, Vec2)> {
+        let mut lines = Vec::new();
+
+        let mut position = Vec2::new(0.0, 0.0);
+        let mut angle = self.starting_angle_rad;
+        let mut stack = Vec::new();
+
+        for c in self.expand().chars() {
+            match c {
+                '+' => { angle += self.angle_rad; }
+                '-' => { angle -= self.angle_rad; }
+                '[' | '(' => { stack.push((position, angle)); }
+                ']' | ')' => {
+                    (position, angle) = stack.pop().expect("L-system pops more than it pushes");
+                }
+                _ => {
+                    if !self.alphabet.contains(&c) { continue; }
+                    let next_position = Vec2::new(position.x() + angle.cos(), position.y() + angle.sin());
+                    if self.draw.get(&c).copied().unwrap_or(false) {
+                        lines.push((position, next_position));
+                    }
+                    position = next_position;
+                }
+            }
+        }
+
+        lines
+    }
+
+    /// Walks the expanded string with a 3D turtle that starts at the origin heading along x
+    /// with its left along y and up along z.
+    /// `+`/`-` turn left/right, `^`/`&` pitch up/down, `\`/`/` roll left/right and `|` turns around.
+    /// `[`/`(` push and `]`/`)` pop the turtle state.
+    pub fn interpret_3d(&self) -> Vec<TurtleSegment> {
+        let mut segments = Vec::new();
+
+        let mut position = Vec4::new_point(0.0, 0.0, 0.0);
+        let mut heading = Vec4::new_vec4(1.0, 0.0, 0.0);
+        let mut left = Vec4::new_vec4(0.0, 1.0, 0.0);
+        let mut up = Vec4::new_vec4(0.0, 0.0, 1.0);
+        let mut stack = Vec::new();
+
+        let (sin, cos) = self.angle_rad.sin_cos();
+        // rotates a towards b by the L-system angle, or away from it when sign is negative
+        let rotate = |a: &Vec4, b: &Vec4, sign: f32| -> (Vec4, Vec4) {
+            (a.scale(cos).add(&b.scale(sign*sin)), a.scale(-sign*sin).add(&b.scale(cos)))
+        };
+
+        for c in self.expand().chars() {
+            match c {
+                '+' => { (heading, left) = rotate(&heading, &left, 1.0); }
+                '-' => { (heading, left) = rotate(&heading, &left, -1.0); }
+                '^' => { (heading, up) = rotate(&heading, &up, 1.0); }
+                '&' => { (heading, up) = rotate(&heading, &up, -1.0); }
+                '\\' => { (left, up) = rotate(&left, &up, -1.0); }
+                '/' => { (left, up) = rotate(&left, &up, 1.0); }
+                '|' => {
+                    heading = heading.neg();
+                    left = left.neg();
+                }
+                '[' | '(' => { stack.push((position, heading, left, up)); }
+                ']' | ')' => {
+                    (position, heading, left, up) = stack.pop().expect("L-system pops more than it pushes");
+                }
+                _ => {
+                    if !self.alphabet.contains(&c) { continue; }
+                    let next_position = position.add(&heading);
+                    if self.draw.get(&c).copied().unwrap_or(false) {
+                        segments.push(TurtleSegment{ start: position, end: next_position, left, up });
+                    }
+                    position = next_position;
+                }
+            }
+        }
+
+        segments
+    }
+}
+
+/// Splits the file in `Key = value` entries, where a value is either the rest of the line
+/// or everything between a pair of curly braces.
+fn parse_entries(text: &str) -> HashMap<String, String> {
+    let mut entries = HashMap::new();
+    let mut rest = text;
+
+    while let Some(equals_pos) = rest.find('=') {
+        let key = rest[..equals_pos].trim().to_string();
+        let after = rest[equals_pos+1..].trim_start();
+
+        let (value, remaining) = if let Some(block) = after.strip_prefix('{') {
+            let end = block.find('}').unwrap_or_else(|| panic!("L-system block {key} isn't closed"));
+            (&block[..end], &block[end+1..])
+        }
+        else {
+            let end = after.find('\n').unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        entries.insert(key, value.trim().to_string());
+        rest = remaining;
+    }
+
+    entries
+}
+
+fn choose_rule<'a>(rules: &'a [(f32, String)], random: &mut Random) -> &'a str {
+    if rules.len() == 1 {
+        return &rules[0].1;
+    }
+
+    let total: f32 = rules.iter().map(|(probability, _)| probability).sum();
+    let mut choice = random.next_f32() * total;
+    for (probability, replacement) in rules.iter() {
+        if choice < *probability {
+            return replacement;
+        }
+        choice -= probability;
+    }
+    &rules[rules.len()-1].1
+}
+
+fn split_block(block: &str) -> Vec<&str> {
+    block.split([',', '\n']).map(|entry| entry.trim()).filter(|entry| !entry.is_empty()).collect()
+}
+
+fn split_rule(entry: &str) -> (char, &str) {
+    let (letter, value) = entry.split_once("->")
+        .unwrap_or_else(|| panic!("expected `letter -> value` in L-system, got: {entry}"));
+    (single_char(letter), value.trim())
+}
+
+fn single_char(s: &str) -> char {
+    let mut chars = s.trim().chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => panic!("L-system letters must be a single character, got: {s}"),
+    }
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s).to_string()
+}
+
+#[test]
+fn test_l_system_3d() {
+    let l_system = LSystem::parse("
+        Alphabet = {F, X}
+        Draw = {
+            F -> 1
+            X -> 0
+        }
+        Rules = {
+            F -> \"F^F\"
+        }
+        Initiator = \"F[+F]X\"
+        Angle = 90
+        Iterations = 1
+    ");
+    assert_eq!(l_system.expand(), "F^F[+F^F]X");
+
+    let segments = l_system.interpret_3d();
+    assert_eq!(segments.len(), 4);
+    // after pitching up the second line points along z
+    assert!((segments[1].end.z() - 1.0).abs() < 1e-5 && (segments[1].end.x() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_stochastic_l_system_2d() {
+    let l_system = LSystem::parse("
+        Alphabet = {F}
+        Draw = {F -> 1}
+        Rules = {
+            F -> 0.5 \"F+F\"
+            F -> 0.5 \"F-F\"
+        }
+        Initiator = \"F\"
+        Angle = 90
+        StartingAngle = 90
+        Iterations = 6
+        Seed = 7
+    ");
+    assert_eq!(l_system.rules[&'F'].len(), 2);
+
+    let expanded = l_system.expand();
+    assert_eq!(expanded.matches('F').count(), 64);
+    assert!(expanded.contains('+') && expanded.contains('-'));
+    // the same seed gives the same string
+    assert_eq!(expanded, l_system.expand());
+
+    let lines = l_system.interpret_2d();
+    assert_eq!(lines.len(), 64);
+    // the turtle starts heading up
+    assert!((lines[0].1.y() - 1.0).abs() < 1e-5 && lines[0].1.x().abs() < 1e-5);
+}
@@ -0,0 +1,128 @@
+use bmp::Image;
+use crate::color::Color;
+use crate::vec2::Vec2;
+
+/// Draws the lines anti-aliased on an image whose largest side is `size` pixels.
+/// The lines are scaled to fill 95% of the image and centered.
+pub fn draw_lines_2d(lines: &[(Vec2, Vec2)], size: u32, background_color: &Color, line_color: &Color) -> Image {
+    let points = lines.iter().flat_map(|(a, b)| [a, b]);
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = (f32::INFINITY, f32::NEG_INFINITY, f32::INFINITY, f32::NEG_INFINITY);
+    for p in points {
+        x_min = x_min.min(p.x());
+        x_max = x_max.max(p.x());
+        y_min = y_min.min(p.y());
+        y_max = y_max.max(p.y());
+    }
+    if lines.is_empty() {
+        (x_min, x_max, y_min, y_max) = (0.0, 1.0, 0.0, 1.0);
+    }
+
+    // a perfectly horizontal or vertical drawing still needs a non zero range
+    let x_range = (x_max - x_min).max(f32::EPSILON);
+    let y_range = (y_max - y_min).max(f32::EPSILON);
+    let largest_range = x_range.max(y_range);
+
+    let image_width = ((size as f32 * x_range / largest_range) as u32).max(1);
+    let image_height = ((size as f32 * y_range / largest_range) as u32).max(1);
+
+    let scaling = 0.95 * size as f32 / largest_range;
+    let offset_x = image_width as f32 / 2.0 - scaling * (x_min + x_max) / 2.0;
+    let offset_y = image_height as f32 / 2.0 - scaling * (y_min + y_max) / 2.0;
+
+    let mut image = Image::new(image_width, image_height);
+    for (x, y) in image.coordinates() {
+        image.set_pixel(x, y, background_color.to_pixel());
+    }
+
+    for (a, b) in lines.iter() {
+        let a = Vec2::new(a.x() * scaling + offset_x, a.y() * scaling + offset_y);
+        let b = Vec2::new(b.x() * scaling + offset_x, b.y() * scaling + offset_y);
+        draw_line_anti_aliased(&mut image, &a, &b, line_color);
+    }
+
+    image
+}
+
+/// Xiaolin Wu's line algorithm, every pixel is blended with the line color by how much
+/// of it the line covers. The origin of the image is its bottom left corner.
+pub fn draw_line_anti_aliased(image: &mut Image, a: &Vec2, b: &Vec2, color: &Color) {
+    let steep = (b.y() - a.y()).abs() > (b.x() - a.x()).abs();
+
+    // walk along the longest axis, from left to right
+    let (mut x0, mut y0, mut x1, mut y1) = if steep {
+        (a.y(), a.x(), b.y(), b.x())
+    } else {
+        (a.x(), a.y(), b.x(), b.y())
+    };
+    if x0 > x1 {
+        (x0, x1) = (x1, x0);
+        (y0, y1) = (y1, y0);
+    }
+
+    let gradient = if x1 - x0 == 0.0 { 1.0 } else { (y1 - y0) / (x1 - x0) };
+
+    let mut plot = |x: f32, y: f32, coverage: f32| {
+        let (x, y) = if steep { (y, x) } else { (x, y) };
+        blend_pixel(image, x, y, color, coverage);
+    };
+
+    // the end points only cover part of their pixel
+    let x_start = x0.round();
+    let y_start = y0 + gradient * (x_start - x0);
+    let x_gap = 1.0 - (x0 + 0.5).fract();
+    plot(x_start, y_start.floor(), (1.0 - y_start.fract()) * x_gap);
+    plot(x_start, y_start.floor() + 1.0, y_start.fract() * x_gap);
+
+    let x_end = x1.round();
+    let y_end = y1 + gradient * (x_end - x1);
+    let x_gap = (x1 + 0.5).fract();
+    plot(x_end, y_end.floor(), (1.0 - y_end.fract()) * x_gap);
+    plot(x_end, y_end.floor() + 1.0, y_end.fract() * x_gap);
+
+    let mut y = y_start + gradient;
+    let mut x = x_start + 1.0;
+    while x < x_end {
+        plot(x, y.floor(), 1.0 - y.fract());
+        plot(x, y.floor() + 1.0, y.fract());
+        y += gradient;
+        x += 1.0;
+    }
+}
+
+fn blend_pixel(image: &mut Image, x: f32, y: f32, color: &Color, coverage: f32) {
+    if x < 0.0 || y < 0.0 || x >= image.get_width() as f32 || y >= image.get_height() as f32 {
+        return;
+    }
+    let (x, y) = (x as u32, image.get_height() - 1 - y as u32);
+
+    let old = image.get_pixel(x, y);
+    let blend = |old: u8, new: f32| old as f32 / 255.0 * (1.0 - coverage) + new * coverage;
+    let blended = Color::new(blend(old.r, color.r()), blend(old.g, color.g()), blend(old.b, color.b()));
+    image.set_pixel(x, y, blended.to_pixel());
+}
+
+#[test]
+fn test_draw_lines_2d() {
+    let lines = vec![
+        (Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0)),
+        (Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0)),
+    ];
+    let image = draw_lines_2d(&lines, 100, &Color::new(0.0, 0.0, 0.0), &Color::new(1.0, 1.0, 1.0));
+    assert_eq!(image.get_width(), 100);
+    assert_eq!(image.get_height(), 50);
+
+    // the horizontal line runs through the bottom of the image, the corner above it stays empty
+    let lit = |x: u32, y: u32| image.get_pixel(x, image.get_height() - 1 - y).r > 0;
+    assert!((0..5).any(|y| lit(50, y)));
+    assert!(!lit(50, 40));
+
+    // a vertical drawing gets an image a pixel wide, with its lines scaled along the height
+    let lines = vec![
+        (Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.1)),
+        (Vec2::new(0.0, 0.9), Vec2::new(0.0, 1.0)),
+    ];
+    let image = draw_lines_2d(&lines, 100, &Color::new(0.0, 0.0, 0.0), &Color::new(1.0, 1.0, 1.0));
+    assert_eq!((image.get_width(), image.get_height()), (1, 100));
+    let lit = |y: u32| image.get_pixel(0, image.get_height() - 1 - y).r > 0;
+    assert!(lit(5) && lit(95) && !lit(50));
+}
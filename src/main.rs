@@ -3,27 +3,90 @@ extern crate core;
 mod vec4;
 
 use std::cmp::max;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::f32::consts::PI;
 use vec4::Vec4;
 
 mod figure;
-use figure::Mesh;
+use figure::{Mesh, NormalWeighting};
+use uv_mapping::UvProjection;
 
 mod matrix4;
 mod vec2;
 mod color;
 mod ini_reader;
+mod points_reader;
+mod l_system;
+mod random;
+mod lines_2d;
+mod noise;
+mod heightmap;
+mod expression;
+mod implicit;
+mod marching_cubes;
+mod patch;
+mod teapot;
+mod convex_hull;
+mod point3;
+mod curve;
+mod triangulation;
+mod subdivision;
+mod simplify;
+mod smoothing;
+mod half_edge;
+mod validation;
+mod csg;
+mod bounds;
+mod slicing;
+mod uv_mapping;
+mod attributes;
+mod mass_properties;
+mod obj_reader;
 
 use color::Color;
 
 use bmp::*;
 use crate::figure::Figure;
-use crate::ini_reader::IniConfiguration;
+use crate::ini_reader::{IniConfiguration, Section};
 use crate::matrix4::{Matrix4, PolarCoord};
 use crate::vec2::Vec2;
+use crate::l_system::LSystem;
+use crate::heightmap::Heightmap;
+use crate::implicit::{ImplicitField, SdfPrimitive};
+use crate::expression::Expression;
+use crate::patch::{BezierPatch, BSplineSurface};
+use crate::curve::Curve;
+use crate::csg::CsgOperation;
+use crate::bounds::{Aabb, BoundingSphere};
+use crate::obj_reader::Material;
+
+const USAGE: &str = "usage:
+    renderer-3d-rust <scene.ini> <image.bmp>
+    renderer-3d-rust slice <scene.ini> <slices.svg | slices.txt> [number of layers, 20 by default]
+    renderer-3d-rust measure <scene.ini>";
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["measure", path_to_ini] => print!("{}", measure_ini_file(path_to_ini)),
+        [path_to_ini, path_to_output_image] => generate_image_from_ini_file(path_to_ini, path_to_output_image),
+        ["slice", path_to_ini, path_to_output, rest @ ..] if rest.len() <= 1 => {
+            let layers_amt = match rest.first().map(|layers| layers.parse::<u32>()) {
+                None => 20,
+                Some(Ok(layers_amt)) if layers_amt > 0 => layers_amt,
+                Some(_) => {
+                    eprintln!("the number of layers must be a positive whole number\n{USAGE}");
+                    std::process::exit(1);
+                }
+            };
+            slice_ini_file(path_to_ini, path_to_output, layers_amt);
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    }
 }
 
 trait Light {
@@ -40,19 +103,52 @@ struct Eye {
 
 enum FigureType {
     Tetrahedron(),
+    Cube(),
+    Octahedron(),
+    Icosahedron(),
+    Dodecahedron(),
+    Fractal(Box<FigureType>, u32, f32), // base figure, iterations, fractal_scale
+    MengerSponge(u32), // iterations
+    LSystem3D(LSystem, f32, u32), // l_system, tube_radius, tube_sides
+    Terrain(Heightmap, f32, f32), // heightmap, cell_size, height_scale
+    Implicit(ImplicitField, Vec4, Vec4, u32), // field, bounds_min, bounds_max, resolution
+    BezierPatches(Vec<BezierPatch>, u32), // patches, resolution
+    BSplineSurface(BSplineSurface, u32), // surface, resolution
+    ConvexHull(Vec<Vec4>), // points
+    Rock(u32, f32, u64), // points_amt, roughness, seed
     Torus(f32, f32, u32, u32), // radius, ring_radius, rings_amt, ring_points_amt
+    TubeSweep(Curve, f32, u32, u32), // curve, tube_radius, segments, sides
+    Lathe(Vec<Vec2>, u32), // profile, steps
+    Extrusion(Vec<Vec2>, Vec4, u32, f32, f32), // profile, direction, steps, twist_rad, end_scale
+    Csg(CsgOperation, Vec<(FigureType, Matrix4)>), // operation, operands with their transformation
+    Obj(Mesh, Option<Material>), // mesh, the material of its first usemtl
 }
 
-struct FigureDescription {
-    figure_type: FigureType,
+/// One copy of a figure's mesh, placed in the world by its transformation
+struct Instance {
+    transform: Matrix4,
     ambient_reflection: Color,
     diffuse_reflection: Color,
     specular_reflection: Color,
-    center: Vec4,
-    scale:  f32,
-    rotation_x_rad: f32,
-    rotation_y_rad: f32,
-    rotation_z_rad: f32,
+}
+
+struct FigureDescription {
+    figure_type: FigureType,
+    mesh_key: String, // the keys that shape the mesh, figures with the same key share their mesh
+    instances: Vec<Instance>,
+    crease_angle_rad: Option<f32>, // None keeps the normals the figure comes with
+    imported: bool, // read from a file, so it gets validated before rendering
+    repair_holes: Option<usize>, // repair the mesh, filling holes of at most this many edges
+    weld_epsilon: Option<f32>, // merge vertices closer than this before anything else
+    subdivisions: u32,
+    smooth_iterations: u32,
+    smooth_lambda: f32,
+    smooth_mu: Option<f32>, // smooth with Taubin's method instead of plain Laplacian smoothing
+    pin_boundary: bool, // keep the boundary vertices in place while smoothing
+    color_by: Option<(String, Color, Color)>, // color the vertices by a named scalar, from the low to the high color
+    target_face_count: Option<usize>, // simplify the figure down to this many triangles
+    normal_weighting: NormalWeighting,
+    uv_projection: Option<UvProjection>, // replace the texture coordinates with projected ones
 }
 
 struct SceneDescription {
@@ -61,6 +157,70 @@ struct SceneDescription {
     eye: Eye,
 }
 
+impl SceneDescription {
+    /// The box around all figures as they are placed in the world, None without any vertices.
+    /// Every figure is generated for this, like for rendering.
+    fn bounds(&self) -> Option<Aabb> {
+        scene_figures(self).iter()
+            .filter_map(|fig| fig.world_mesh().aabb())
+            .reduce(|a, b| a.union(&b))
+    }
+}
+
+/// Renders the 3D scene described by the ini file, or draws its 2D L-system
+/// when the General section has `type = "2DLSystem"`
+fn generate_image_from_ini_file(path_to_ini: &str, path_to_output_image: &str) {
+    let configuration = IniConfiguration::new(path_to_ini);
+    let general = configuration.get_section("General").unwrap();
+
+    if general.key_exists("type") && general.as_string_or_die("type") == "2DLSystem" {
+        let l_system = LSystem::from_file(&general.as_string_or_die("inputfile"));
+        let size = general.as_f32_or_default("size", 1024.0) as u32;
+        let background_color = general.as_tuple_or_default("backgroundcolor", [0.0;3]);
+        let background_color = Color::new(background_color[0], background_color[1], background_color[2]);
+        let color = general.as_tuple_or_default("color", [1.0;3]);
+        let color = Color::new(color[0], color[1], color[2]);
+
+        let image = lines_2d::draw_lines_2d(&l_system.interpret_2d(), size, &background_color, &color);
+        image.save(path_to_output_image).unwrap_or_else(|_| panic!("writing image: {path_to_output_image} to file failed"));
+        return;
+    }
+
+    let scene = read_scene_description_from_ini_file(path_to_ini);
+    render_scene(&scene, path_to_output_image);
+}
+
+/// Slices all figures of the scene, placed in the world like for rendering, with a stack of horizontal
+/// planes and writes the cross sections as svg or, for any other extension, as text
+fn slice_ini_file(path_to_ini: &str, path_to_output: &str, layers_amt: u32) {
+    let scene = read_scene_description_from_ini_file(path_to_ini);
+    let meshes: Vec<Mesh> = scene_figures(&scene).iter().map(Figure::world_mesh).collect();
+    let layers = slicing::slice_stack(&meshes, layers_amt);
+    let output = if path_to_output.ends_with(".svg") { slicing::to_svg(&layers) } else { slicing::to_text(&layers) };
+    std::fs::write(path_to_output, output).unwrap_or_else(|_| panic!("writing slices: {path_to_output} to file failed"));
+}
+
+/// The area, volume, centroid and inertia tensor of every figure of the scene, placed in the world
+/// like for rendering, with every copy of an instanced figure on its own, followed by the corners of
+/// the box around the whole scene
+fn measure_ini_file(path_to_ini: &str) -> String {
+    let scene = read_scene_description_from_ini_file(path_to_ini);
+    let names = scene.figures.iter().enumerate().flat_map(|(figure_i, figure_desc)| {
+        let copies_amt = figure_desc.instances.len();
+        (0..copies_amt).map(move |copy_i| {
+            if copies_amt == 1 { format!("Figure{figure_i}") } else { format!("Figure{figure_i} copy {copy_i}") }
+        })
+    });
+    let mut report: String = names.zip(scene_figures(&scene))
+        .map(|(name, figure)| format!("{name}\n{}", mass_properties::report(&figure.world_mesh())))
+        .collect();
+    if let Some(bounds) = scene.bounds() {
+        report += &format!("Scene\n  lowest corner {} {} {}\n  highest corner {} {} {}\n",
+            bounds.min.x(), bounds.min.y(), bounds.min.z(), bounds.max.x(), bounds.max.y(), bounds.max.z());
+    }
+    report
+}
+
 fn read_scene_description_from_ini_file(path_to_ini: &str) -> SceneDescription {
 
     let configuration = IniConfiguration::new(path_to_ini);
@@ -87,52 +247,112 @@ fn read_scene_description_from_ini_file(path_to_ini: &str) -> SceneDescription {
     for i in 0..figures_amt {
         let figure_section = configuration.get_section(&format!("Figure{i}")).unwrap();
 
-        let figure_type = figure_section.as_string_or_die("type");
+        let figure_type = read_figure_type(figure_section, &configuration);
 
-        let mut figure_type = match figure_type.as_str() {
-            "Tetrahedron" => { FigureType::Tetrahedron() },
-            "Torus"       => {
-                let radius = figure_section.as_f32_or_die("R");
-                let ring_radius = figure_section.as_f32_or_die("r");
-                let rings_amt = figure_section.as_f32_or_die("n") as u32;
-                let ring_points_amt = figure_section.as_f32_or_die("m") as u32;
-                FigureType::Torus(radius, ring_radius, rings_amt, ring_points_amt)
-            }
-            _ => {
-                println!("Too bad. I don't have your requested shape. How about a torus instead?");
-                FigureType::Torus(5.0, 1.0, 20, 20)
-            }
+        // imported figures take their colors from their material, unless the section sets them
+        let material = if let FigureType::Obj(_, material) = &figure_type { *material } else { None };
+        let read_color = |key: &str| {
+            let color = figure_section.as_tuple_or_die(key);
+            Color::new(color[0], color[1], color[2])
+        };
+        let color_or = |key: &str, default: Color| {
+            if figure_section.key_exists(key) { read_color(key) } else { default }
         };
 
         let ambient_reflection = if figure_section.key_exists("color") {
-            figure_section.as_tuple_or_die("color")
+            read_color("color")
         }
         else {
-            figure_section.as_tuple_or_die("ambientReflection")
+            match material {
+                Some(material) => color_or("ambientReflection", material.ambient),
+                None => read_color("ambientReflection"),
+            }
         };
-        let ambient_reflection = Color::new(ambient_reflection[0], ambient_reflection[1], ambient_reflection[2]);
-
-        let diffuse_reflection = figure_section.as_tuple_or_default("diffuseReflection", [0.0;3]);
-        let diffuse_reflection = Color::new(diffuse_reflection[0], diffuse_reflection[1], diffuse_reflection[2]);
-
-        let specular_reflection = figure_section.as_tuple_or_default("specularReflection", [0.0;3]);
-        let specular_reflection = Color::new(specular_reflection[0], specular_reflection[1], specular_reflection[2]);
-
-        let center = figure_section.as_tuple_or_default("center", [0.0;3]);
-        let center = Vec4::new_vec4(center[0], center[1], center[2]);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let diffuse_reflection = color_or("diffuseReflection", material.map_or(black, |material| material.diffuse));
+        let specular_reflection = color_or("specularReflection", material.map_or(black, |material| material.specular));
 
-        let scale = figure_section.as_f32_or_default("scale", 1.0);
+        let instances = read_instances(figure_section, Instance{
+            transform: read_placement(figure_section),
+            ambient_reflection, diffuse_reflection, specular_reflection,
+        });
+        let mesh_key = figure_section.fingerprint(&PLACEMENT_KEYS);
 
-        let rotation_x_rad = figure_section.as_f32_or_default("rotateX", 0.0).to_radians();
-        let rotation_y_rad = figure_section.as_f32_or_default("rotateY", 0.0).to_radians();
-        let rotation_z_rad = figure_section.as_f32_or_default("rotateZ", 0.0).to_radians();
+        let crease_angle_rad = if figure_section.key_exists("creaseAngle") {
+            Some(figure_section.as_f32_or_die("creaseAngle").to_radians())
+        }
+        else {
+            None
+        };
+        let imported = figure_section.key_exists("file");
+        let repair_holes = if figure_section.key_exists("repair") {
+            Some(figure_section.as_f32_or_die("repair") as usize)
+        }
+        else {
+            None
+        };
+        let weld_epsilon = if figure_section.key_exists("weld") {
+            Some(figure_section.as_f32_or_die("weld"))
+        }
+        else {
+            None
+        };
+        let subdivisions = figure_section.as_f32_or_default("subdivisions", 0.0) as u32;
+        let smooth_iterations = figure_section.as_f32_or_default("smoothIterations", 0.0) as u32;
+        let smooth_lambda = figure_section.as_f32_or_default("smoothLambda", 0.5);
+        let smooth_mu = if figure_section.key_exists("smoothMu") {
+            Some(figure_section.as_f32_or_die("smoothMu"))
+        }
+        else {
+            None
+        };
+        let pin_boundary = figure_section.as_f32_or_default("pinBoundary", 0.0) != 0.0;
+        let color_by = if figure_section.key_exists("colorBy") {
+            let low = figure_section.as_tuple_or_default("colorLow", [0.0, 0.0, 1.0]);
+            let high = figure_section.as_tuple_or_default("colorHigh", [1.0, 0.0, 0.0]);
+            Some((figure_section.as_string_or_die("colorBy"), Color::new(low[0], low[1], low[2]), Color::new(high[0], high[1], high[2])))
+        }
+        else {
+            None
+        };
+        let target_face_count = if figure_section.key_exists("targetFaceCount") {
+            Some(figure_section.as_f32_or_die("targetFaceCount") as usize)
+        }
+        else {
+            None
+        };
+        let normal_weighting = if figure_section.key_exists("normalWeighting") {
+            match figure_section.as_string_or_die("normalWeighting").as_str() {
+                "Area" => NormalWeighting::Area,
+                "Angle" => NormalWeighting::Angle,
+                other => panic!("normalWeighting must be \"Area\" or \"Angle\", not \"{other}\""),
+            }
+        }
+        else {
+            NormalWeighting::Angle
+        };
+        let uv_projection = if figure_section.key_exists("uvProjection") {
+            Some(match figure_section.as_string_or_die("uvProjection").as_str() {
+                "Planar" => {
+                    let direction = figure_section.as_tuple_or_default("uvDirection", [0.0, 0.0, 1.0]);
+                    UvProjection::Planar(Vec4::new_vec4(direction[0], direction[1], direction[2]))
+                }
+                "Spherical" => UvProjection::Spherical,
+                "Cylindrical" => UvProjection::Cylindrical,
+                "Box" => UvProjection::Box,
+                other => panic!("uvProjection must be \"Planar\", \"Spherical\", \"Cylindrical\" or \"Box\", not \"{other}\""),
+            })
+        }
+        else {
+            None
+        };
 
         figures.push(FigureDescription{
             figure_type,
-            center,
-            scale,
-            rotation_x_rad, rotation_y_rad, rotation_z_rad,
-            ambient_reflection, diffuse_reflection, specular_reflection,
+            mesh_key,
+            instances,
+            crease_angle_rad, normal_weighting, imported, repair_holes, weld_epsilon, subdivisions, target_face_count,
+            smooth_iterations, smooth_lambda, smooth_mu, pin_boundary, color_by, uv_projection,
         });
     }
 
@@ -141,6 +361,495 @@ fn read_scene_description_from_ini_file(path_to_ini: &str) -> SceneDescription {
     SceneDescription{figures, lights, eye}
 }
 
+/// Reads the kind of figure and its parameters from a figure section. CSG figures read their
+/// operands from the sections they name.
+fn read_figure_type(figure_section: &Section, configuration: &IniConfiguration) -> FigureType {
+    let figure_type = figure_section.as_string_or_die("type");
+
+    match figure_type.as_str() {
+        "Tetrahedron" => { FigureType::Tetrahedron() },
+        "Cube"        => { FigureType::Cube() },
+        "Octahedron"  => { FigureType::Octahedron() },
+        "Icosahedron" => { FigureType::Icosahedron() },
+        "Dodecahedron" => { FigureType::Dodecahedron() },
+        "FractalTetrahedron" | "FractalCube" | "FractalOctahedron" | "FractalIcosahedron" | "FractalDodecahedron" => {
+            let base = match figure_type.strip_prefix("Fractal").unwrap() {
+                "Tetrahedron" => FigureType::Tetrahedron(),
+                "Cube"        => FigureType::Cube(),
+                "Octahedron"  => FigureType::Octahedron(),
+                "Icosahedron" => FigureType::Icosahedron(),
+                _             => FigureType::Dodecahedron(),
+            };
+            let iterations = figure_section.as_f32_or_die("nrIterations") as u32;
+            let fractal_scale = figure_section.as_f32_or_die("fractalScale");
+            FigureType::Fractal(Box::new(base), iterations, fractal_scale)
+        }
+        "LSystem3D"   => {
+            let l_system = LSystem::from_file(&figure_section.as_string_or_die("inputfile"));
+            let tube_radius = figure_section.as_f32_or_default("tubeRadius", 0.05);
+            let tube_sides = figure_section.as_f32_or_default("tubeSides", 6.0) as u32;
+            FigureType::LSystem3D(l_system, tube_radius, tube_sides)
+        }
+        "Terrain"     => {
+            let heightmap = if figure_section.key_exists("heightmap") {
+                Heightmap::from_bmp(&figure_section.as_string_or_die("heightmap"))
+            }
+            else {
+                Heightmap::from_noise(
+                    figure_section.as_f32_or_default("width", 64.0) as usize,
+                    figure_section.as_f32_or_default("depth", 64.0) as usize,
+                    figure_section.as_f32_or_default("seed", 0.0) as u64,
+                    figure_section.as_f32_or_default("frequency", 0.05),
+                    figure_section.as_f32_or_default("octaves", 4.0) as u32,
+                    figure_section.as_f32_or_default("lacunarity", 2.0),
+                    figure_section.as_f32_or_default("gain", 0.5),
+                )
+            };
+            let cell_size = figure_section.as_f32_or_default("cellSize", 1.0);
+            let height_scale = figure_section.as_f32_or_default("heightScale", 1.0);
+            FigureType::Terrain(heightmap, cell_size, height_scale)
+        }
+        "Implicit"    => {
+            let field = read_implicit_field(figure_section);
+            let bounds_min = figure_section.as_tuple_or_default("boundsMin", [-2.0;3]);
+            let bounds_min = Vec4::new_point(bounds_min[0], bounds_min[1], bounds_min[2]);
+            let bounds_max = figure_section.as_tuple_or_default("boundsMax", [2.0;3]);
+            let bounds_max = Vec4::new_point(bounds_max[0], bounds_max[1], bounds_max[2]);
+            let resolution = figure_section.as_f32_or_default("resolution", 32.0) as u32;
+            FigureType::Implicit(field, bounds_min, bounds_max, resolution)
+        }
+        "BezierPatches" | "Teapot" => {
+            let patches = if figure_type == "Teapot" {
+                teapot::teapot_patches()
+            }
+            else {
+                patch::read_bezier_patches(&figure_section.as_string_or_die("file"))
+            };
+            let resolution = figure_section.as_f32_or_default("resolution", 8.0) as u32;
+            FigureType::BezierPatches(patches, resolution)
+        }
+        "BSplineSurface" => {
+            let surface = patch::read_b_spline_surface(&figure_section.as_string_or_die("file"));
+            let resolution = figure_section.as_f32_or_default("resolution", 8.0) as u32;
+            FigureType::BSplineSurface(surface, resolution)
+        }
+        "ConvexHull"  => {
+            FigureType::ConvexHull(points_reader::read_points_3d(&figure_section.as_string_or_die("file")))
+        }
+        "Rock"        => {
+            let points_amt = figure_section.as_f32_or_default("nrPoints", 50.0) as u32;
+            let roughness = figure_section.as_f32_or_default("roughness", 0.3);
+            let seed = figure_section.as_f32_or_default("seed", 0.0) as u64;
+            FigureType::Rock(points_amt, roughness, seed)
+        }
+        "MengerSponge" => {
+            FigureType::MengerSponge(figure_section.as_f32_or_die("nrIterations") as u32)
+        }
+        "Obj"         => {
+            // taken as they are, obj files are usually y up and stand up in this z up world with rotateX = 90
+            let (mesh, material) = obj_reader::read_obj(&figure_section.as_string_or_die("file"));
+            FigureType::Obj(mesh, material)
+        }
+        "Torus"       => {
+            let radius = figure_section.as_f32_or_die("R");
+            let ring_radius = figure_section.as_f32_or_die("r");
+            let rings_amt = figure_section.as_f32_or_die("n") as u32;
+            let ring_points_amt = figure_section.as_f32_or_die("m") as u32;
+            FigureType::Torus(radius, ring_radius, rings_amt, ring_points_amt)
+        }
+        "TorusKnot" | "Helix" | "Spring" => {
+            let tube_radius = figure_section.as_f32_or_default("tubeRadius", 0.2);
+            let segments = figure_section.as_f32_or_default("n", 100.0) as u32;
+            let sides = figure_section.as_f32_or_default("m", 12.0) as u32;
+            let curve = match figure_type.as_str() {
+                "TorusKnot" => Curve::TorusKnot(
+                    figure_section.as_f32_or_default("p", 2.0) as u32,
+                    figure_section.as_f32_or_default("q", 3.0) as u32,
+                    figure_section.as_f32_or_default("R", 2.0),
+                    figure_section.as_f32_or_default("r", 1.0),
+                ),
+                "Helix" => Curve::Helix(
+                    figure_section.as_f32_or_default("radius", 1.0),
+                    figure_section.as_f32_or_default("pitch", 1.0),
+                    figure_section.as_f32_or_default("turns", 3.0),
+                ),
+                _ => Curve::Spring(
+                    figure_section.as_f32_or_default("radius", 1.0),
+                    figure_section.as_f32_or_default("pitch", 1.0),
+                    figure_section.as_f32_or_default("turns", 5.0),
+                    figure_section.as_f32_or_default("endPitch", 2.0 * tube_radius),
+                ),
+            };
+            FigureType::TubeSweep(curve, tube_radius, segments, sides)
+        }
+        "Lathe"       => {
+            let profile = read_profile(figure_section);
+            let steps = figure_section.as_f32_or_die("n") as u32;
+            FigureType::Lathe(profile, steps)
+        }
+        "Extrusion"   => {
+            let profile = read_profile(figure_section);
+            let direction = figure_section.as_tuple_or_default("direction", [0.0, 0.0, 1.0]);
+            let direction = Vec4::new_vec4(direction[0], direction[1], direction[2]);
+            let steps = figure_section.as_f32_or_default("n", 1.0) as u32;
+            let twist_rad = figure_section.as_f32_or_default("twist", 0.0).to_radians();
+            let end_scale = figure_section.as_f32_or_default("endScale", 1.0);
+            FigureType::Extrusion(profile, direction, steps, twist_rad, end_scale)
+        }
+        "CSG"         => {
+            let operation = match figure_section.as_string_or_die("operation").as_str() {
+                "Union" => CsgOperation::Union,
+                "Intersection" => CsgOperation::Intersection,
+                "Difference" => CsgOperation::Difference,
+                other => panic!("CSG operation must be \"Union\", \"Intersection\" or \"Difference\", not \"{other}\""),
+            };
+            let operands_amt = figure_section.as_f32_or_die("nrOperands") as u32;
+            if operands_amt < 2 {
+                panic!("a CSG figure needs at least two operands, got {operands_amt}");
+            }
+            let operands = (0..operands_amt)
+                .map(|i| {
+                    let section_name = figure_section.as_string_or_die(&format!("operand{i}"));
+                    let operand_section = configuration.get_section(&section_name)
+                        .unwrap_or_else(|_| panic!("CSG operand section [{section_name}] doesn't exist"));
+                    (read_figure_type(operand_section, configuration), read_transform(operand_section))
+                })
+                .collect();
+            FigureType::Csg(operation, operands)
+        }
+        _ => {
+            println!("Too bad. I don't have your requested shape. How about a torus instead?");
+            FigureType::Torus(5.0, 1.0, 20, 20)
+        }
+    }
+}
+
+/// The keys of a figure that place and color it, they don't change its mesh
+const PLACEMENT_KEYS: [&str; 15] = [
+    "center", "scale", "rotateX", "rotateY", "rotateZ",
+    "color", "ambientReflection", "diffuseReflection", "specularReflection",
+    "instances", "gridSize", "spacing", "nrInstances", "ringRadius", "instanceColor",
+];
+
+/// The copies of a figure, `instances` = "Grid" lays them out in a grid of `gridSize` copies along x, y
+/// and z, `spacing` apart and centered around the figure's place; "Ring" puts `nrInstances` copies on a
+/// circle of `ringRadius` around the figure's z axis, turned to face outward. Without `instances` the
+/// figure is drawn once. The ambient reflection of the copies blends from the figure's color to
+/// `instanceColor` when given.
+fn read_instances(figure_section: &Section, base: Instance) -> Vec<Instance> {
+    let local_transforms: Vec<Matrix4> = if !figure_section.key_exists("instances") {
+        vec![Matrix4::new_identity()]
+    }
+    else {
+        match figure_section.as_string_or_die("instances").as_str() {
+            "Grid" => {
+                let size = figure_section.as_tuple_or_die("gridSize").map(|amt| (amt as usize).max(1));
+                let spacing = figure_section.as_tuple_or_die("spacing");
+                let offset = |axis: usize, i: usize| (i as f32 - (size[axis] - 1) as f32 / 2.0) * spacing[axis];
+                let mut transforms = Vec::new();
+                for z in 0..size[2] {
+                    for y in 0..size[1] {
+                        for x in 0..size[0] {
+                            transforms.push(Matrix4::new_translation(&Vec4::new_vec4(offset(0, x), offset(1, y), offset(2, z))));
+                        }
+                    }
+                }
+                transforms
+            }
+            "Ring" => {
+                let instances_amt = figure_section.as_f32_or_die("nrInstances").max(1.0) as u32;
+                let ring_radius = figure_section.as_f32_or_die("ringRadius");
+                (0..instances_amt).map(|i| {
+                    let angle_rad = 2.0 * PI * i as f32 / instances_amt as f32;
+                    // negate angle cuz counter clockwise rotation
+                    Matrix4::mul(&Matrix4::new_translation(&Vec4::new_vec4(ring_radius, 0.0, 0.0)), &Matrix4::new_rotation_z(-angle_rad))
+                }).collect()
+            }
+            other => panic!("instances must be \"Grid\" or \"Ring\", not \"{other}\""),
+        }
+    };
+
+    let last_color = figure_section.as_tuple_or_default("instanceColor", [base.ambient_reflection.r(), base.ambient_reflection.g(), base.ambient_reflection.b()]);
+    let instances_amt = local_transforms.len();
+    local_transforms.into_iter().enumerate().map(|(i, local_transform)| {
+        let t = if instances_amt > 1 { i as f32 / (instances_amt - 1) as f32 } else { 0.0 };
+        let a = base.ambient_reflection;
+        Instance{
+            transform: Matrix4::mul(&local_transform, &base.transform),
+            ambient_reflection: Color::new(
+                a.r() + (last_color[0] - a.r()) * t, a.g() + (last_color[1] - a.g()) * t, a.b() + (last_color[2] - a.b()) * t),
+            ..base
+        }
+    }).collect()
+}
+
+/// The transformation of a CSG operand: scaled by `scale`, rotated by `rotateX`, `rotateY` and `rotateZ`
+/// degrees around those axes in that order, then moved to `center`
+fn read_transform(section: &Section) -> Matrix4 {
+    let center = section.as_tuple_or_default("center", [0.0;3]);
+    figure_transform(
+        section.as_f32_or_default("scale", 1.0),
+        [
+            section.as_f32_or_default("rotateX", 0.0).to_radians(),
+            section.as_f32_or_default("rotateY", 0.0).to_radians(),
+            section.as_f32_or_default("rotateZ", 0.0).to_radians(),
+        ],
+        &Vec4::new_vec4(center[0], center[1], center[2]),
+    )
+}
+
+/// Where a figure is placed: turned by `rotateX` and `rotateZ` degrees around those axes, then moved
+/// to `center`. Unlike CSG operands, figures aren't scaled or turned around y.
+fn read_placement(section: &Section) -> Matrix4 {
+    let center = section.as_tuple_or_default("center", [0.0;3]);
+    figure_transform(
+        1.0,
+        [
+            section.as_f32_or_default("rotateX", 0.0).to_radians(),
+            0.0,
+            section.as_f32_or_default("rotateZ", 0.0).to_radians(),
+        ],
+        &Vec4::new_vec4(center[0], center[1], center[2]),
+    )
+}
+
+fn figure_transform(scale: f32, rotation_rad: [f32; 3], center: &Vec4) -> Matrix4 {
+    // negate angles cuz counter clockwise rotation
+    let mut transform = Matrix4::new_scaling(scale);
+    transform = Matrix4::mul(&transform, &Matrix4::new_rotation_x(-rotation_rad[0]));
+    transform = Matrix4::mul(&transform, &Matrix4::new_rotation_y(-rotation_rad[1]));
+    transform = Matrix4::mul(&transform, &Matrix4::new_rotation_z(-rotation_rad[2]));
+    Matrix4::mul(&transform, &Matrix4::new_translation(center))
+}
+
+/// A profile is either given inline with the `profile` key or read from the file named by `profileFile`.
+fn read_profile(figure_section: &Section) -> Vec<Vec2> {
+    if figure_section.key_exists("profileFile") {
+        points_reader::read_points_2d(&figure_section.as_string_or_die("profileFile"))
+    }
+    else {
+        points_reader::parse_points_2d(&figure_section.as_string_or_die("profile"))
+    }
+}
+
+/// The `field` key picks the kind of field:
+/// "Metaballs" reads `nrBalls` balls with keys `ball{i}` (center) and `ballRadius{i}`,
+/// "SDF" reads `nrPrimitives` primitives with keys `primitive{i}` ("Sphere", "Box" or "Torus"),
+/// `primitiveCenter{i}`, `primitiveRadius{i}`, `primitiveSize{i}` and `primitiveRingRadius{i}`
+/// that are merged with a smooth union over `smoothness`,
+/// "Expression" reads the function of x, y and z in `expression`, negative inside the surface.
+fn read_implicit_field(figure_section: &Section) -> ImplicitField {
+    let as_point = |key: &str| {
+        let p = figure_section.as_tuple_or_default(key, [0.0;3]);
+        Vec4::new_point(p[0], p[1], p[2])
+    };
+
+    match figure_section.as_string_or_die("field").as_str() {
+        "Metaballs" => {
+            let balls_amt = figure_section.as_f32_or_die("nrBalls") as u32;
+            let balls = (0..balls_amt)
+                .map(|i| (as_point(&format!("ball{i}")), figure_section.as_f32_or_default(&format!("ballRadius{i}"), 1.0)))
+                .collect();
+            ImplicitField::Metaballs(balls)
+        }
+        "SDF" => {
+            let primitives_amt = figure_section.as_f32_or_die("nrPrimitives") as u32;
+            let primitives = (0..primitives_amt).map(|i| {
+                let center = as_point(&format!("primitiveCenter{i}"));
+                let radius = figure_section.as_f32_or_default(&format!("primitiveRadius{i}"), 1.0);
+                match figure_section.as_string_or_die(&format!("primitive{i}")).as_str() {
+                    "Box" => {
+                        let size = figure_section.as_tuple_or_default(&format!("primitiveSize{i}"), [1.0;3]);
+                        SdfPrimitive::Box(center, Vec4::new_vec4(size[0], size[1], size[2]))
+                    }
+                    "Torus" => {
+                        let ring_radius = figure_section.as_f32_or_default(&format!("primitiveRingRadius{i}"), 0.25);
+                        SdfPrimitive::Torus(center, radius, ring_radius)
+                    }
+                    _ => SdfPrimitive::Sphere(center, radius),
+                }
+            }).collect();
+            ImplicitField::Sdf(primitives, figure_section.as_f32_or_default("smoothness", 0.0))
+        }
+        _ => {
+            let expression = figure_section.as_string_or_die("expression");
+            ImplicitField::Expression(Expression::parse(&expression).unwrap_or_else(|err| panic!("{err}")))
+        }
+    }
+}
+
+fn generate_mesh(figure_type: &FigureType) -> Mesh {
+    match figure_type {
+        FigureType::Tetrahedron() => { Mesh::new_tetrahedron() }
+        FigureType::Cube() => { Mesh::new_cube() }
+        FigureType::Octahedron() => { Mesh::new_octahedron() }
+        FigureType::Icosahedron() => { Mesh::new_icosahedron() }
+        FigureType::Dodecahedron() => { Mesh::new_dodecahedron() }
+        FigureType::Fractal(base, iterations, fractal_scale) => {
+            Mesh::new_fractal(&generate_mesh(base), *iterations, *fractal_scale)
+        }
+        FigureType::MengerSponge(iterations) => { Mesh::new_menger_sponge(*iterations) }
+        FigureType::LSystem3D(l_system, tube_radius, tube_sides) => {
+            Mesh::new_l_system_3d(l_system, *tube_radius, *tube_sides)
+        }
+        FigureType::Terrain(heightmap, cell_size, height_scale) => {
+            Mesh::new_terrain(heightmap, *cell_size, *height_scale)
+        }
+        FigureType::Implicit(field, bounds_min, bounds_max, resolution) => {
+            Mesh::new_implicit(field, bounds_min, bounds_max, *resolution)
+        }
+        FigureType::BezierPatches(patches, resolution) => {
+            Mesh::new_parametric_surfaces(patches, *resolution)
+        }
+        FigureType::BSplineSurface(surface, resolution) => {
+            Mesh::new_parametric_surfaces(std::slice::from_ref(surface), *resolution)
+        }
+        FigureType::ConvexHull(points) => { Mesh::new_convex_hull(points) }
+        FigureType::Rock(points_amt, roughness, seed) => { Mesh::new_rock(*points_amt, *roughness, *seed) }
+        FigureType::Torus(radius, ring_radius, rings_amt, ring_points_amt) => {
+            Mesh::new_torus(*radius, *ring_radius, *rings_amt, *ring_points_amt)
+        }
+        FigureType::TubeSweep(curve, tube_radius, segments, sides) => {
+            Mesh::new_tube_sweep(curve, *tube_radius, *segments, *sides)
+        }
+        FigureType::Lathe(profile, steps) => { Mesh::new_lathe(profile, *steps) }
+        FigureType::Extrusion(profile, direction, steps, twist_rad, end_scale) => {
+            Mesh::new_extrusion(profile, direction, *steps, *twist_rad, *end_scale)
+        }
+        FigureType::Csg(operation, operands) => {
+            let mut meshes = operands.iter().map(|(operand, transform)| {
+                let mut mesh = generate_mesh(operand);
+                mesh.transform(transform);
+                mesh
+            });
+            let first = meshes.next().unwrap();
+            meshes.fold(first, |result, mesh| match operation {
+                CsgOperation::Union => result.union(&mesh),
+                CsgOperation::Intersection => result.intersection(&mesh),
+                CsgOperation::Difference => result.difference(&mesh),
+            })
+        }
+        FigureType::Obj(mesh, _) => { mesh.clone() }
+    }
+}
+
+/// Generates the figure's mesh and runs it through welding, repairing, smoothing, subdivision,
+/// simplification and normals
+fn prepare_mesh(figure_i: usize, figure_desc: &FigureDescription) -> Mesh {
+    let mut mesh = generate_mesh(&figure_desc.figure_type);
+
+    if let Some(epsilon) = figure_desc.weld_epsilon {
+        mesh.weld(epsilon);
+    }
+    if figure_desc.imported {
+        let report = mesh.validate_surface();
+        if !report.is_valid() {
+            for problem in report.problems() {
+                eprintln!("warning: figure {figure_i}: {problem}");
+            }
+        }
+    }
+    if let Some(max_hole_edges) = figure_desc.repair_holes {
+        mesh.repair(max_hole_edges);
+    }
+    // faces pointing past the vertices can't be drawn
+    let vertices_amt = mesh.vertices.len();
+    mesh.faces.retain(|face| face.indexes.iter().all(|&i| i < vertices_amt));
+
+    if figure_desc.smooth_iterations > 0 {
+        let smoothed = match figure_desc.smooth_mu {
+            Some(mu) => mesh.smooth_taubin(figure_desc.smooth_iterations, figure_desc.smooth_lambda, mu, figure_desc.pin_boundary),
+            None => mesh.smooth_laplacian(figure_desc.smooth_iterations, figure_desc.smooth_lambda, figure_desc.pin_boundary),
+        };
+        if let Err(message) = smoothed {
+            eprintln!("warning: figure {figure_i}: {message}");
+        }
+    }
+
+    // meshes of only triangles are subdivided with Loop's scheme, others with Catmull-Clark,
+    // edges sharper than the crease angle stay sharp
+    if figure_desc.subdivisions > 0 {
+        if let Some(crease_angle_rad) = figure_desc.crease_angle_rad {
+            mesh.mark_creases(crease_angle_rad);
+        }
+        if mesh.faces.iter().all(|face| face.indexes.len() == 3) {
+            mesh.subdivide_loop(figure_desc.subdivisions);
+        }
+        else {
+            mesh.subdivide_catmull_clark(figure_desc.subdivisions);
+        }
+    }
+
+    if let Some(target_face_count) = figure_desc.target_face_count {
+        mesh.simplify(target_face_count);
+    }
+
+    // figures that come without normals are smoothed up to a crease angle of 60 degrees
+    if let Some(crease_angle_rad) = figure_desc.crease_angle_rad {
+        mesh.compute_normals(figure_desc.normal_weighting, crease_angle_rad);
+    }
+    else if mesh.attributes.normals.is_empty() {
+        mesh.compute_normals(figure_desc.normal_weighting, 60f32.to_radians());
+    }
+
+    // after the normals, which the box projection picks its sides by
+    if let Some(projection) = figure_desc.uv_projection {
+        mesh.project_uvs(projection);
+    }
+
+    // "height" is the z coordinate when the figure doesn't come with heights of its own
+    if let Some((name, low, high)) = &figure_desc.color_by {
+        if name == "height" && !mesh.attributes.scalars.contains_key(name) {
+            let heights = mesh.vertices.iter().map(|v| v.z()).collect();
+            mesh.attributes.scalars.insert(name.clone(), heights);
+        }
+        if let Err(message) = mesh.color_by_scalar(name, low, high) {
+            eprintln!("warning: figure {figure_i}: {message}");
+        }
+    }
+
+    let failed_faces = mesh.triangulate();
+    if !failed_faces.is_empty() {
+        eprintln!("warning: figure {figure_i}: {} faces couldn't be triangulated properly", failed_faces.len());
+    }
+    mesh
+}
+
+/// Every copy of every figure, figures with the same mesh key share one mesh
+fn scene_figures(scene_desc: &SceneDescription) -> Vec<Figure> {
+    let mut meshes: HashMap<&str, (Rc<Mesh>, Option<BoundingSphere>)> = HashMap::new();
+    let mut figures = Vec::new();
+    for (figure_i, figure_desc) in scene_desc.figures.iter().enumerate() {
+        let (mesh, bounding_sphere) = meshes.entry(&figure_desc.mesh_key).or_insert_with(|| {
+            let mesh = prepare_mesh(figure_i, figure_desc);
+            let bounding_sphere = mesh.bounding_sphere();
+            (Rc::new(mesh), bounding_sphere)
+        });
+        for instance in figure_desc.instances.iter() {
+            figures.push(Figure{
+                mesh: Rc::clone(mesh),
+                bounding_sphere: *bounding_sphere,
+                transform: instance.transform,
+                ambient_reflection: instance.ambient_reflection,
+                diffuse_reflection: instance.diffuse_reflection,
+                specular_reflection: instance.specular_reflection,
+            });
+        }
+    }
+    figures
+}
+
+/// Whether the sphere, in eye coordinates, lies entirely behind the eye or beyond one of the sides
+/// of the view, which spans `right` and `top` at a distance of 1 down the negative z axis
+fn outside_view(sphere: &BoundingSphere, right: f32, top: f32) -> bool {
+    let c = sphere.center;
+    let sides = [
+        Vec4::new_vec4(1.0, 0.0, right), Vec4::new_vec4(-1.0, 0.0, right),
+        Vec4::new_vec4(0.0, 1.0, top), Vec4::new_vec4(0.0, -1.0, top),
+    ];
+    c.z() - sphere.radius >= 0.0 || sides.iter().any(|side| side.normalize().dot(&c) > sphere.radius)
+}
+
 fn render_scene(scene_desc: &SceneDescription, path_to_output_image: &str) {
 
     let aspect_ratio = scene_desc.eye.aspect_ratio; // width / height
@@ -165,37 +874,29 @@ fn render_scene(scene_desc: &SceneDescription, path_to_output_image: &str) {
     //let eye_point_transform = Matrix4::new_eye_point_transform_looking_at_origin(&eye_pos);
     let eye_point_transform = Matrix4::new_eye_point_transform(&eye_pos, &looking_dir);
 
-    for figure_desc in scene_desc.figures.iter() {
-
-        let fig_mesh = match figure_desc.figure_type {
-            FigureType::Tetrahedron() => { Mesh::new_tetrahedron() }
-            FigureType::Torus(radius, ring_radius, rings_amt, ring_points_amt) => {
-                Mesh::new_torus(radius, ring_radius, rings_amt, ring_points_amt)
-            }
-        };
-
-        let mut fig = Figure{
-            mesh: fig_mesh,
-            ambient_reflection: figure_desc.ambient_reflection,
-            diffuse_reflection: figure_desc.diffuse_reflection,
-            specular_reflection: figure_desc.specular_reflection,
-        };
-
-        fig.mesh.triangulate();
-        fig.mesh.transform(&Matrix4::new_rotation_x(-figure_desc.rotation_x_rad)); // negate angle cuz counter clockwise rotation
-        fig.mesh.transform(&Matrix4::new_rotation_z(-figure_desc.rotation_z_rad)); // negate angle cuz counter clockwise rotation
-        //todo: rotate around y
-        fig.mesh.transform(&Matrix4::new_translation(&figure_desc.center));
-
-        fig.mesh.transform(&Matrix4::new_eye_point_transform(&scene_desc.eye.pos, &scene_desc.eye.looking_dir));
+    for fig in scene_figures(scene_desc).iter() {
+        let transform = Matrix4::mul(&fig.transform, &Matrix4::new_eye_point_transform(&scene_desc.eye.pos, &scene_desc.eye.looking_dir));
+        if fig.bounding_sphere.is_none_or(|sphere| outside_view(&sphere.transform(&transform), right, top)) {
+            continue;
+        }
 
+        // the mesh is shared between copies, only its vertices are transformed for this one
+        let vertices: Vec<Vec4> = fig.mesh.vertices.iter().map(|v| v.mul(&transform)).collect();
+        let colors = &fig.mesh.attributes.colors;
         for face in fig.mesh.faces.iter() {
-            let a = &fig.mesh.vertices[face.indexes[0]];
-            let b = &fig.mesh.vertices[face.indexes[1]];
-            let c = &fig.mesh.vertices[face.indexes[2]];
+            let a = &vertices[face.indexes[0]];
+            let b = &vertices[face.indexes[1]];
+            let c = &vertices[face.indexes[2]];
+            let vertex_colors = (!colors.is_empty()).then(|| [0, 1, 2].map(|corner| colors[face.indexes[corner]]));
+            let shading = Shading{
+                ambient_reflection: fig.ambient_reflection,
+                diffuse_reflection: fig.diffuse_reflection,
+                specular_reflection: fig.specular_reflection,
+                vertex_colors,
+            };
             draw_triangle(a, b, c,
                           viewport_scaling, &viewport_offset,
-                          &fig.ambient_reflection, &fig.diffuse_reflection, &fig.specular_reflection,
+                          &shading,
                           &mut image);
         }
     }
@@ -209,6 +910,138 @@ fn test_scene_rendering() {
     render_scene(&scene, "tori.bmp");
 }
 
+#[test]
+fn test_csg_operand_transform() {
+    let path = std::env::temp_dir().join("test_csg_operand_transform.ini");
+    std::fs::write(&path, "[Operand]\ntype = \"Cube\"\nscale = 2\nrotateY = 90\ncenter = (0, 0, -10)\n").unwrap();
+    let configuration = IniConfiguration::new(path.to_str().unwrap());
+    let operand_section = configuration.get_section("Operand").unwrap();
+    // scaled, then turned a quarter around y counter clockwise, which takes x to -z
+    let turned = Vec4::new_point(1.0, 0.0, 0.0).mul(&read_transform(operand_section));
+    assert!(turned.x().abs() < 1e-5 && turned.y().abs() < 1e-5 && (turned.z() + 12.0).abs() < 1e-5);
+    // figures only take the center
+    let placed = Vec4::new_point(1.0, 0.0, 0.0).mul(&read_placement(operand_section));
+    assert!((placed.x() - 1.0).abs() < 1e-5 && placed.y().abs() < 1e-5 && (placed.z() + 10.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_scene_bounds() {
+    let scene = read_scene_description_from_ini_file("tori.ini");
+    let bounds = scene.bounds().unwrap();
+    assert!(scene.figures.iter().all(|figure_desc| {
+        figure_desc.instances.iter().all(|instance| {
+            let center = Vec4::new_point(0.0, 0.0, 0.0).mul(&instance.transform);
+            [(center.x(), bounds.min.x(), bounds.max.x()), (center.y(), bounds.min.y(), bounds.max.y()), (center.z(), bounds.min.z(), bounds.max.z())]
+                .iter().all(|(c, min, max)| min <= c && c <= max)
+        })
+    }));
+
+    let sphere = BoundingSphere{center: Vec4::new_point(0.0, 0.0, -10.0), radius: 1.0};
+    assert!(!outside_view(&sphere, 0.25, 0.25));
+    assert!(outside_view(&BoundingSphere{center: Vec4::new_point(0.0, 0.0, 10.0), ..sphere}, 0.25, 0.25));
+    assert!(outside_view(&BoundingSphere{center: Vec4::new_point(5.0, 0.0, -10.0), ..sphere}, 0.25, 0.25));
+}
+
+#[test]
+fn test_instancing() {
+    let path = std::env::temp_dir().join("test_instancing.ini");
+    std::fs::write(&path, "[General]\nnrFigures = 3\n\
+        [Figure0]\ntype = \"Cube\"\ncolor = (1, 0, 0)\ninstances = \"Grid\"\ngridSize = (3, 2, 1)\nspacing = (4, 4, 4)\ninstanceColor = (0, 0, 1)\n\
+        [Figure1]\ntype = \"Cube\"\ncolor = (0, 1, 0)\ncenter = (0, 0, 10)\ninstances = \"Ring\"\nnrInstances = 4\nringRadius = 5\n\
+        [Figure2]\ntype = \"Octahedron\"\ncolor = (0, 1, 0)\n").unwrap();
+    let scene = read_scene_description_from_ini_file(path.to_str().unwrap());
+    let figures = scene_figures(&scene);
+    assert_eq!(figures.len(), 6 + 4 + 1);
+    // the cubes all share one mesh
+    assert!(figures[..10].iter().all(|fig| Rc::ptr_eq(&fig.mesh, &figures[0].mesh)));
+    assert!(!Rc::ptr_eq(&figures[0].mesh, &figures[10].mesh));
+
+    let origin = Vec4::new_point(0.0, 0.0, 0.0);
+    assert_eq!(origin.mul(&figures[0].transform), Vec4::new_point(-4.0, -2.0, 0.0));
+    assert_eq!(origin.mul(&figures[5].transform), Vec4::new_point(4.0, 2.0, 0.0));
+    assert!((figures[0].ambient_reflection.r() - 1.0).abs() < 1e-6 && (figures[5].ambient_reflection.b() - 1.0).abs() < 1e-6);
+    let on_ring = origin.mul(&figures[8].transform);
+    assert!((on_ring.x() + 5.0).abs() < 1e-5 && on_ring.y().abs() < 1e-5 && (on_ring.z() - 10.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_measuring() {
+    let path = std::env::temp_dir().join("test_measuring.ini");
+    std::fs::write(&path, "[General]\nnrFigures = 2\n\
+        [Figure0]\ntype = \"Cube\"\ncolor = (1, 1, 1)\ncenter = (3, 0, 0)\n\
+        [Figure1]\ntype = \"Cube\"\ncolor = (1, 1, 1)\ninstances = \"Grid\"\ngridSize = (2, 1, 1)\nspacing = (4, 4, 4)\n").unwrap();
+    let report = measure_ini_file(path.to_str().unwrap());
+    let values = |key: &str| -> Vec<Vec<f32>> {
+        report.lines()
+            .filter_map(|line| line.trim().strip_prefix(key))
+            .map(|rest| rest.split_whitespace().map(|value| value.parse().unwrap()).collect())
+            .collect()
+    };
+    assert!(report.starts_with("Figure0\n") && report.contains("Figure1 copy 1\n"));
+    let volumes = values("volume ");
+    assert_eq!(volumes.len(), 3);
+    assert!((volumes[0][0] - 8.0).abs() < 1e-4 && (volumes[2][0] - 8.0).abs() < 1e-4);
+    let centroids = values("centroid ");
+    assert!((centroids[0][0] - 3.0).abs() < 1e-4 && (centroids[2][0] - 2.0).abs() < 1e-4);
+    // the copies of Figure1 lie from x = -3 to 3, Figure0 from 2 to 4
+    assert_eq!(values("lowest corner "), vec![vec![-3.0, -1.0, -1.0]]);
+    assert_eq!(values("highest corner "), vec![vec![4.0, 1.0, 1.0]]);
+}
+
+#[test]
+fn test_obj_figures() {
+    let directory = std::env::temp_dir().join("test_obj_figures");
+    std::fs::create_dir_all(&directory).unwrap();
+    // a cube with a normal and texture coordinates per face, so every corner is split in three
+    let mut obj = String::from("mtllib cube.mtl\nusemtl red\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n");
+    let cube = Mesh::new_cube();
+    for v in cube.vertices.iter() {
+        obj += &format!("v {} {} {}\n", v.x(), v.y(), v.z());
+    }
+    for face in cube.faces.iter() {
+        let n = cube.face_normal(face).normalize();
+        obj += &format!("vn {} {} {}\nf", n.x(), n.y(), n.z());
+        for (corner, &i) in face.indexes.iter().enumerate() {
+            obj += &format!(" {}/{}/-1", i + 1, corner + 1);
+        }
+        obj += "\n";
+    }
+    std::fs::write(directory.join("cube.obj"), obj).unwrap();
+    std::fs::write(directory.join("cube.mtl"), "newmtl red\nKa 1 0 0\nKd 0.5 0 0\nKs 0.25\nNs 10\n").unwrap();
+    let path = directory.join("scene.ini");
+    let file = directory.join("cube.obj");
+    std::fs::write(&path, format!("[General]\nnrFigures = 3\n\
+        [Figure0]\ntype = \"Obj\"\nfile = \"{0}\"\n\
+        [Figure1]\ntype = \"Obj\"\nfile = \"{0}\"\ncenter = (5, 0, 0)\ncolor = (0, 0, 1)\ndiffuseReflection = (0, 1, 0)\n\
+        [Figure2]\ntype = \"Obj\"\nfile = \"{0}\"\nuvProjection = \"Planar\"\nuvDirection = (0, 0, 1)\n",
+        file.display())).unwrap();
+
+    let scene = read_scene_description_from_ini_file(path.to_str().unwrap());
+    let figures = scene_figures(&scene);
+    let (red, blue) = (&figures[0], &figures[1]);
+    assert!((red.ambient_reflection.r() - 1.0).abs() < 1e-6 && (red.diffuse_reflection.r() - 0.5).abs() < 1e-6);
+    assert!((red.specular_reflection.g() - 0.25).abs() < 1e-6);
+    assert!((blue.ambient_reflection.b() - 1.0).abs() < 1e-6 && (blue.diffuse_reflection.g() - 1.0).abs() < 1e-6);
+    assert!((blue.specular_reflection.g() - 0.25).abs() < 1e-6);
+
+    // the quads are triangulated, the normals and texture coordinates from the file kept
+    let mesh = &red.mesh;
+    assert_eq!(mesh.vertices.len(), 24);
+    assert_eq!(mesh.faces.len(), 12);
+    assert_eq!(mesh.attributes.uvs.len(), 24);
+    assert!(mesh.faces.iter().all(|face| {
+        let n = mesh.face_normal(face).normalize();
+        face.indexes.iter().all(|&i| mesh.attributes.normals[i].dot(&n) > 0.999)
+    }));
+    assert!((mesh.volume() - 8.0).abs() < 1e-4);
+
+    // projected from above, the coordinates from the file are replaced by the x and y of the box
+    let projected = &figures[2].mesh;
+    assert!(!Rc::ptr_eq(&figures[2].mesh, &red.mesh));
+    assert!(projected.vertices.iter().zip(projected.attributes.uvs.iter())
+        .all(|(p, uv)| (uv.x() - (p.x() + 1.0) / 2.0).abs() < 1e-5 && (uv.y() - (p.y() + 1.0) / 2.0).abs() < 1e-5));
+}
+
 #[test]
 fn test_rendering_stuff() {
 
@@ -242,7 +1075,8 @@ fn test_rendering_stuff() {
     let diffuse_reflection = ambient_reflection;
     let specular_reflection = ambient_reflection;
 
-    let fig = Figure{ mesh, ambient_reflection, diffuse_reflection, specular_reflection };
+    let bounding_sphere = mesh.bounding_sphere();
+    let fig = Figure{ mesh: Rc::new(mesh), bounding_sphere, transform: Matrix4::new_identity(), ambient_reflection, diffuse_reflection, specular_reflection };
 
     figures.push(fig);
 
@@ -255,9 +1089,15 @@ fn test_rendering_stuff() {
             let a = &fig.mesh.vertices[face.indexes[0]];
             let b = &fig.mesh.vertices[face.indexes[1]];
             let c = &fig.mesh.vertices[face.indexes[2]];
+            let shading = Shading{
+                ambient_reflection: fig.ambient_reflection,
+                diffuse_reflection: fig.diffuse_reflection,
+                specular_reflection: fig.specular_reflection,
+                vertex_colors: None,
+            };
             draw_triangle(a, b, c,
                           viewport_scaling, &viewport_offset,
-                          &fig.ambient_reflection, &fig.diffuse_reflection, &fig.specular_reflection,
+                          &shading,
                           &mut image);
         }
     }
@@ -267,9 +1107,18 @@ fn test_rendering_stuff() {
 
 
 
+/// How a triangle is colored: by the reflections of its figure, or by the colors of its corners
+/// spread over it when the mesh has them
+struct Shading {
+    ambient_reflection: Color,
+    diffuse_reflection: Color,
+    specular_reflection: Color,
+    vertex_colors: Option<[Color; 3]>,
+}
+
 fn draw_triangle(a: &Vec4, b: &Vec4, c: &Vec4,
                  viewport_scaling: f32, viewport_offset: &Vec2,
-                 ambient_reflection: &Color, diffuse_reflection: &Color, specular_reflection: &Color,
+                 shading: &Shading,
                  image: &mut Image) {
     // project a, b and c to screen space
     let proj_a = project_point(a, viewport_scaling, viewport_offset);
@@ -281,16 +1130,41 @@ fn draw_triangle(a: &Vec4, b: &Vec4, c: &Vec4,
     let min_y = proj_y_values.iter().fold(f32::INFINITY, |a, &b| a.min(b)) as u32;
     let max_y = proj_y_values.iter().fold(-f32::INFINITY, |a, &b| a.max(b)) as u32;
 
-    let mut reflected_color = *ambient_reflection;
+    let mut reflected_color = shading.ambient_reflection;
 
     for y_i in min_y..=max_y {
         // determine where to start drawing the horizontal "scanline" and where to end
         let (x_l, x_r) = calculate_scanline(y_i, &proj_a, &proj_b, &proj_c);
         for x_i in x_l..=x_r {
+            if let Some(colors) = shading.vertex_colors {
+                let weights = perspective_weights(x_i as f32, y_i as f32, [&proj_a, &proj_b, &proj_c], [a.z(), b.z(), c.z()]);
+                reflected_color = Color::new(
+                    (0..3).map(|i| colors[i].r() * weights[i]).sum(),
+                    (0..3).map(|i| colors[i].g() * weights[i]).sum(),
+                    (0..3).map(|i| colors[i].b() * weights[i]).sum(),
+                );
+            }
             image.put_pixel(x_i, y_i, reflected_color.to_pixel());
         }
     }
 
+    /// How much each corner counts at the pixel. Barycentric coordinates on the screen are divided by
+    /// the depth of their corner, so values are spread evenly over the triangle in space and not on the screen.
+    fn perspective_weights(x: f32, y: f32, corners: [&Vec2; 3], depths: [f32; 3]) -> [f32; 3] {
+        let [p, q, r] = corners;
+        let area = (q.x() - p.x()) * (r.y() - p.y()) - (r.x() - p.x()) * (q.y() - p.y());
+        if area == 0.0 { return [1.0 / 3.0; 3]; }
+        let screen = [
+            ((q.x() - x) * (r.y() - y) - (r.x() - x) * (q.y() - y)) / area,
+            ((r.x() - x) * (p.y() - y) - (p.x() - x) * (r.y() - y)) / area,
+            ((p.x() - x) * (q.y() - y) - (q.x() - x) * (p.y() - y)) / area,
+        ];
+        let weights = [0, 1, 2].map(|i| screen[i].clamp(0.0, 1.0) / depths[i]);
+        let total: f32 = weights.iter().sum();
+        if total == 0.0 { return [1.0 / 3.0; 3]; }
+        weights.map(|weight| weight / total)
+    }
+
     fn calculate_scanline(y: u32, proj_a: &Vec2, proj_b: &Vec2, proj_c: &Vec2) -> (u32, u32) {
         let mut x_l_ab = f32::MAX;
         let mut x_l_ac = f32::MAX;
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use crate::figure::{CUBE_CORNERS, CUBE_FACES, Face, Mesh};
+use crate::vec4::Vec4;
+
+/// Polygonizes the surface where `field` is zero, the field is negative inside.
+/// The box between `min` and `max` is split in `resolution` cells along every axis.
+///
+/// Instead of the usual 256 entry triangle table every cell is traced face by face: on every cube
+/// face the edges where the field changes sign are joined by segments, and the segments of all six
+/// faces are chained into the polygons of the cell. A face with four sign changes is resolved by the
+/// value in its center. Neighbouring cells see the same values on their shared face and resolve it
+/// the same way, so the surface has no cracks.
+pub fn polygonize(field: impl Fn(&Vec4) -> f32, min: &Vec4, max: &Vec4, resolution: u32) -> Mesh {
+    let cells = resolution.max(1) as usize;
+    let points = cells + 1;
+    let cell_size = [
+        (max.x() - min.x()) / cells as f32,
+        (max.y() - min.y()) / cells as f32,
+        (max.z() - min.z()) / cells as f32,
+    ];
+
+    let point_index = |x: usize, y: usize, z: usize| (z * points + y) * points + x;
+    let position = |x: usize, y: usize, z: usize| Vec4::new_point(
+        min.x() + x as f32 * cell_size[0],
+        min.y() + y as f32 * cell_size[1],
+        min.z() + z as f32 * cell_size[2],
+    );
+
+    let mut values = Vec::with_capacity(points * points * points);
+    for z in 0..points {
+        for y in 0..points {
+            for x in 0..points {
+                values.push(field(&position(x, y, z)));
+            }
+        }
+    }
+
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+    // vertex on every grid edge the surface crosses, keyed by the edge's lowest grid point and axis
+    let mut edge_vertices: HashMap<(usize, usize), usize> = HashMap::new();
+
+    for z in 0..cells {
+        for y in 0..cells {
+            for x in 0..cells {
+                let corner_point = |corner: usize| {
+                    let c = CUBE_CORNERS[corner];
+                    (x + c[0] as usize, y + c[1] as usize, z + c[2] as usize)
+                };
+                let corner_value = |corner: usize| {
+                    let (px, py, pz) = corner_point(corner);
+                    values[point_index(px, py, pz)]
+                };
+                let inside = |corner: usize| corner_value(corner) < 0.0;
+
+                let inside_amt = (0..8).filter(|&c| inside(c)).count();
+                if inside_amt == 0 || inside_amt == 8 { continue; }
+
+                let mut edge_vertex = |a: usize, b: usize| -> usize {
+                    let (pa, pb) = (corner_point(a), corner_point(b));
+                    let (low, high, low_corner, high_corner) = if pa <= pb { (pa, pb, a, b) } else { (pb, pa, b, a) };
+                    let axis = if low.0 != high.0 { 0 } else if low.1 != high.1 { 1 } else { 2 };
+                    let key = (point_index(low.0, low.1, low.2), axis);
+
+                    *edge_vertices.entry(key).or_insert_with(|| {
+                        let (value_low, value_high) = (corner_value(low_corner), corner_value(high_corner));
+                        let t = value_low / (value_low - value_high);
+                        let p_low = position(low.0, low.1, low.2);
+                        let p_high = position(high.0, high.1, high.2);
+                        vertices.push(p_low.add(&p_high.sub(&p_low).scale(t)));
+                        vertices.len() - 1
+                    })
+                };
+
+                // segments go from where the face boundary enters the inside to where it leaves it
+                let mut segments: Vec<(usize, usize)> = Vec::new();
+
+                for face in CUBE_FACES.iter() {
+                    let mut leaving = Vec::new();
+                    let mut entering = Vec::new();
+                    for k in 0..4 {
+                        let (a, b) = (face[k], face[(k+1)%4]);
+                        if inside(a) && !inside(b) { leaving.push((k, edge_vertex(a, b))); }
+                        if !inside(a) && inside(b) { entering.push((k, edge_vertex(a, b))); }
+                    }
+
+                    if entering.len() == 1 {
+                        segments.push((entering[0].1, leaving[0].1));
+                    }
+                    else if entering.len() == 2 {
+                        // sorted so both cells sharing the face add the values up in the same order
+                        let mut face_values = face.map(corner_value);
+                        face_values.sort_by(f32::total_cmp);
+                        let center: f32 = face_values.iter().sum::<f32>() / 4.0;
+                        // with an inside center the inside corners are connected, so every entering
+                        // crossing joins the leaving crossing before it instead of the one after it
+                        for &(k, entering_vertex) in entering.iter() {
+                            let leaving_vertex = leaving.iter()
+                                .min_by_key(|(leaving_k, _)| {
+                                    if center < 0.0 { (k + 4 - leaving_k) % 4 } else { (leaving_k + 4 - k) % 4 }
+                                })
+                                .unwrap().1;
+                            segments.push((entering_vertex, leaving_vertex));
+                        }
+                    }
+                }
+
+                // chain the segments into closed polygons
+                while let Some((start, mut current)) = segments.pop() {
+                    let mut polygon = vec![start];
+                    while current != start {
+                        polygon.push(current);
+                        let next_i = segments.iter().position(|(from, _)| *from == current)
+                            .expect("marching cubes polygon isn't closed");
+                        current = segments.swap_remove(next_i).1;
+                    }
+
+                    for i in 2..polygon.len() {
+                        faces.push(Face::new(vec![polygon[0], polygon[i-1], polygon[i]]));
+                    }
+                }
+            }
+        }
+    }
+
+    Mesh::new(vertices, faces)
+}
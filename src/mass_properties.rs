@@ -0,0 +1,162 @@
+use crate::figure::Mesh;
+use crate::vec4::Vec4;
+
+/// A symmetric 3 by 3 matrix, like an inertia tensor
+pub type Tensor = [[f32; 3]; 3];
+
+/// The triangles every face is fanned into from its first corner
+fn triangles(mesh: &Mesh) -> impl Iterator<Item = [Vec4; 3]> + '_ {
+    mesh.faces.iter().flat_map(move |face| {
+        (2..face.indexes.len()).map(move |i| [face.indexes[0], face.indexes[i-1], face.indexes[i]].map(|j| mesh.vertices[j]))
+    })
+}
+
+pub fn surface_area(mesh: &Mesh) -> f32 {
+    mesh.faces.iter().map(|face| mesh.face_normal(face).length() / 2.0).sum()
+}
+
+/// The volume enclosed by the faces, by the divergence theorem: the signed volumes of the tetrahedra
+/// from the origin to every triangle add up to it. Negative when the faces point inward.
+pub fn volume(mesh: &Mesh) -> f32 {
+    triangles(mesh).map(|[a, b, c]| a.dot(&b.cross(&c)) / 6.0).sum()
+}
+
+/// The center of mass of the enclosed solid, of even density. None when it encloses no volume.
+pub fn centroid(mesh: &Mesh) -> Option<Vec4> {
+    let volume = volume(mesh);
+    if volume.abs() <= enclosed_volume_epsilon(mesh) { return None; }
+    let moment = triangles(mesh).fold(Vec4::new_vec4(0.0, 0.0, 0.0), |acc, [a, b, c]| {
+        // the centroid of the tetrahedron, weighed by its signed volume
+        acc.add(&a.add(&b).add(&c).scale(a.dot(&b.cross(&c)) / 24.0))
+    });
+    let centroid = moment.scale(1.0 / volume);
+    Some(Vec4::new_point(centroid.x(), centroid.y(), centroid.z()))
+}
+
+/// The inertia tensor around the centroid, with a density of 1 so the mass is the volume. A mesh with
+/// its faces pointing inward gives the same tensor as when they point outward. None when it encloses
+/// no volume.
+pub fn inertia_tensor(mesh: &Mesh) -> Option<Tensor> {
+    let centroid = centroid(mesh)?;
+    let volume = volume(mesh);
+
+    // the covariance of the solid, the integral of x_i * x_j over it, summed over the tetrahedra from
+    // the origin. For a tetrahedron with corners 0, a, b and c it is its signed volume / 20 times
+    // (a a^T + b b^T + c c^T + s s^T), s being a + b + c.
+    let mut covariance = [[0.0; 3]; 3];
+    for [a, b, c] in triangles(mesh) {
+        let weight = a.dot(&b.cross(&c)) / 120.0;
+        let s = a.add(&b).add(&c);
+        for p in [a, b, c, s] {
+            let p = [p.x(), p.y(), p.z()];
+            for i in 0..3 {
+                for j in 0..3 {
+                    covariance[i][j] += weight * p[i] * p[j];
+                }
+            }
+        }
+    }
+
+    // moved to the centroid, then turned from the covariance into the inertia tensor
+    let c = [centroid.x(), centroid.y(), centroid.z()];
+    for i in 0..3 {
+        for j in 0..3 {
+            covariance[i][j] -= volume * c[i] * c[j];
+        }
+    }
+    let trace = covariance[0][0] + covariance[1][1] + covariance[2][2];
+    let sign = volume.signum();
+    let mut inertia = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            inertia[i][j] = sign * (if i == j { trace } else { 0.0 } - covariance[i][j]);
+        }
+    }
+    Some(inertia)
+}
+
+/// Volumes this close to zero are taken as no volume at all, as a flat or empty mesh still adds up to
+/// some rounding error
+fn enclosed_volume_epsilon(mesh: &Mesh) -> f32 {
+    mesh.aabb().map_or(0.0, |bounds| {
+        let size = bounds.size();
+        1e-6 * size.x().max(size.y()).max(size.z()).powi(3)
+    })
+}
+
+/// The measurements of a mesh as lines of text, indented by two spaces
+pub fn report(mesh: &Mesh) -> String {
+    let mut text = format!("  area {}\n  volume {}\n", mesh.surface_area(), mesh.volume());
+    if !mesh.validate_surface().holes.is_empty() {
+        text += "  the mesh has holes, its volume and everything worked out from it are only estimates\n";
+    }
+    match (mesh.centroid(), mesh.inertia_tensor()) {
+        (Some(centroid), Some(inertia)) => {
+            text += &format!("  centroid {} {} {}\n", centroid.x(), centroid.y(), centroid.z());
+            text += "  inertia tensor";
+            for row in inertia.iter() {
+                text += &format!(" [{} {} {}]", row[0], row[1], row[2]);
+            }
+            text += "\n";
+        }
+        _ => text += "  no enclosed volume, so no centroid or inertia tensor\n",
+    }
+    text
+}
+
+#[test]
+fn test_mass_properties() {
+    use std::f32::consts::PI;
+    use crate::figure::Face;
+    use crate::matrix4::Matrix4;
+
+    let close = |a: f32, b: f32| (a - b).abs() < 1e-3 * b.abs().max(1.0);
+
+    // a box of 2 by 4 by 6, moved away from the origin and turned a quarter around z
+    let mut block = Mesh::new_cube();
+    for vertex in block.vertices.iter_mut() {
+        *vertex = Vec4::new_point(vertex.x(), 2.0 * vertex.y(), 3.0 * vertex.z());
+    }
+    let t = Matrix4::mul(&Matrix4::new_rotation_z(PI / 2.0), &Matrix4::new_translation(&Vec4::new_vec4(5.0, -2.0, 1.0)));
+    block.transform(&t);
+    assert!(close(surface_area(&block), 2.0 * (8.0 + 12.0 + 24.0)));
+    assert!(close(volume(&block), 48.0));
+    let center = centroid(&block).unwrap();
+    assert!(close(center.x(), 5.0) && close(center.y(), -2.0) && close(center.z(), 1.0));
+    let inertia = inertia_tensor(&block).unwrap();
+    // m (b^2 + c^2) / 12 around every axis, with the 2 wide side now along y
+    assert!(close(inertia[0][0], 48.0 * (4.0 + 36.0) / 12.0));
+    assert!(close(inertia[1][1], 48.0 * (16.0 + 36.0) / 12.0));
+    assert!(close(inertia[2][2], 48.0 * (16.0 + 4.0) / 12.0));
+    assert!(close(inertia[0][1], 0.0) && close(inertia[1][2], 0.0) && close(inertia[0][2], 0.0));
+
+    // turned inside out, the volume flips but the tensor stays
+    let mut inside_out = block.clone();
+    for face in inside_out.faces.iter_mut() {
+        face.indexes.reverse();
+    }
+    assert!(close(volume(&inside_out), -48.0));
+    let flipped = inertia_tensor(&inside_out).unwrap();
+    assert!((0..3).all(|i| (0..3).all(|j| close(flipped[i][j], inertia[i][j]))));
+
+    // a sphere of radius 1 approached closer and closer, 2/5 m r^2 around every axis
+    let mut sphere = Mesh::new_icosahedron();
+    sphere.subdivide_loop(4);
+    for vertex in sphere.vertices.iter_mut() {
+        *vertex = vertex.normalize();
+    }
+    let mass = volume(&sphere);
+    assert!((mass - 4.0 / 3.0 * PI).abs() < 0.02);
+    let inertia = inertia_tensor(&sphere).unwrap();
+    assert!((0..3).all(|axis| (inertia[axis][axis] / (0.4 * mass) - 1.0).abs() < 0.01));
+    assert!(close(inertia[0][1], 0.0) && close(inertia[1][2], 0.0) && close(inertia[0][2], 0.0));
+
+    // a single square has area but no volume
+    let square = Mesh::new(
+        vec![Vec4::new_point(0.0, 0.0, 0.0), Vec4::new_point(1.0, 0.0, 0.0), Vec4::new_point(1.0, 1.0, 0.0), Vec4::new_point(0.0, 1.0, 0.0)],
+        vec![Face::new(vec![0, 1, 2, 3])],
+    );
+    assert!(close(surface_area(&square), 1.0));
+    assert!(centroid(&square).is_none() && inertia_tensor(&square).is_none());
+    assert!(report(&square).contains("holes"));
+}
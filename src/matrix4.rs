@@ -2,6 +2,7 @@ use std::f32::consts::PI;
 use crate::{Vec4, vec4};
 
 /// we use row vectors
+#[derive(Clone, Copy, Debug)]
 pub struct Matrix4 {
     pub elements: [[f32; 4]; 4],
 }
@@ -41,6 +42,26 @@ impl Matrix4 {
         result
     }
 
+    /// Clock-wise rotation, like the rotations around x and z
+    pub fn new_rotation_y(angle_rad: f32) -> Self {
+        let mut result = Self::new_identity();
+
+        result.elements[0][0] = f32::cos(angle_rad);
+        result.elements[0][2] = f32::sin(angle_rad);
+        result.elements[2][0] = -f32::sin(angle_rad);
+        result.elements[2][2] = f32::cos(angle_rad);
+
+        result
+    }
+
+    pub fn new_scaling(factor: f32) -> Self {
+        let mut result = Self::new_identity();
+        for i in 0..3 {
+            result.elements[i][i] = factor;
+        }
+        result
+    }
+
     pub fn new_translation(translation_vec: &Vec4) -> Self {
         let mut result = Self::new_identity();
         result.elements[3][0] = translation_vec.x();
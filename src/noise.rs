@@ -0,0 +1,88 @@
+use crate::random::Random;
+
+/// Ken Perlin's improved gradient noise in 2D, with a permutation shuffled by the seed
+pub struct PerlinNoise {
+    permutation: [u8; 512],
+}
+
+impl PerlinNoise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        let mut random = Random::new(seed);
+        // Fisher-Yates shuffle
+        for i in (1..table.len()).rev() {
+            let j = (random.next_u64() % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        let mut permutation = [0; 512];
+        for i in 0..512 {
+            permutation[i] = table[i % 256];
+        }
+
+        Self{permutation}
+    }
+
+    /// Noise value in about [-1, 1], zero on every integer grid point
+    pub fn noise(&self, x: f32, y: f32) -> f32 {
+        let (x_floor, y_floor) = (x.floor(), y.floor());
+        let (xi, yi) = ((x_floor as i64 & 255) as usize, (y_floor as i64 & 255) as usize);
+        let (xf, yf) = (x - x_floor, y - y_floor);
+
+        let p = &self.permutation;
+        let hash = |i: usize, j: usize| p[p[xi + i] as usize + yi + j];
+
+        let (u, v) = (fade(xf), fade(yf));
+        let bottom = lerp(gradient(hash(0, 0), xf, yf), gradient(hash(1, 0), xf - 1.0, yf), u);
+        let top = lerp(gradient(hash(0, 1), xf, yf - 1.0), gradient(hash(1, 1), xf - 1.0, yf - 1.0), u);
+        lerp(bottom, top, v)
+    }
+
+    /// Fractal Brownian motion: `octaves` layers of noise, every layer `lacunarity` times
+    /// finer and `gain` times weaker than the previous one
+    pub fn fbm(&self, x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.noise(x * frequency, y * frequency);
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+        sum
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dot product of (x, y) with one of eight gradient directions picked by the hash
+fn gradient(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => x - y,
+        2 => -x + y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[test]
+fn test_perlin_noise() {
+    let noise = PerlinNoise::new(3);
+    assert_eq!(noise.noise(2.0, 5.0), 0.0);
+
+    let values: Vec<f32> = (0..100).map(|i| noise.fbm(i as f32 * 0.37, i as f32 * 0.11, 4, 2.0, 0.5)).collect();
+    assert!(values.iter().all(|v| v.abs() < 2.0));
+    assert!(values.iter().any(|&v| v != values[0]));
+    // the same seed gives the same terrain
+    assert_eq!(values[42], PerlinNoise::new(3).fbm(42.0 * 0.37, 42.0 * 0.11, 4, 2.0, 0.5));
+}
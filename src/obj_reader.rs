@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::color::Color;
+use crate::figure::{Face, Mesh};
+use crate::vec2::Vec2;
+use crate::vec4::Vec4;
+
+/// The colors of a material from an mtl file
+#[derive(Clone, Copy)]
+pub struct Material {
+    pub ambient: Color, // Ka
+    pub diffuse: Color, // Kd
+    pub specular: Color, // Ks
+    pub shininess: f32, // Ns, the specular exponent
+}
+
+impl Default for Material {
+    /// The values the mtl format takes for the keys a material leaves out
+    fn default() -> Self {
+        Self{
+            ambient: Color::new(0.2, 0.2, 0.2),
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::new(1.0, 1.0, 1.0),
+            shininess: 0.0,
+        }
+    }
+}
+
+/// What an obj file holds
+pub struct Obj {
+    pub mesh: Mesh,
+    pub material_libraries: Vec<String>, // the mtl files it refers to, relative to the obj file
+    pub materials: Vec<String>, // the materials its faces use, in the order they first do
+}
+
+/// Parses the faces of an obj file with the positions, normals and texture coordinates of their corners.
+/// Corners are written as `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indexes count back from the
+/// last element read so far. Every combination of position, normal and texture coordinates the faces
+/// use becomes one vertex, so a position is only split where its corners disagree. Normals and texture
+/// coordinates are only kept when every corner has them. Lines, points, groups and smoothing groups
+/// are skipped.
+pub fn parse_obj(text: &str) -> Result<Obj, String> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut corners: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+    let mut vertex_indexes = HashMap::new();
+    let mut faces = Vec::new();
+    let mut material_libraries = Vec::new();
+    let mut materials: Vec<String> = Vec::new();
+
+    for (line_i, line) in text.lines().enumerate() {
+        let line_nr = line_i + 1;
+        let line = line.split('#').next().unwrap();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else { continue };
+        let words: Vec<&str> = words.collect();
+        let numbers = || -> Result<Vec<f32>, String> {
+            words.iter()
+                .map(|word| word.parse::<f32>().map_err(|_| format!("line {line_nr}: \"{word}\" isn't a number")))
+                .collect()
+        };
+
+        match keyword {
+            "v" => {
+                let c = numbers()?;
+                if c.len() < 3 { return Err(format!("line {line_nr}: a position needs 3 coordinates")); }
+                positions.push(Vec4::new_point(c[0], c[1], c[2]));
+            }
+            "vt" => {
+                let c = numbers()?;
+                if c.is_empty() { return Err(format!("line {line_nr}: texture coordinates need at least u")); }
+                uvs.push(Vec2::new(c[0], c.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let c = numbers()?;
+                if c.len() < 3 { return Err(format!("line {line_nr}: a normal needs 3 coordinates")); }
+                let normal = Vec4::new_vec4(c[0], c[1], c[2]);
+                normals.push(if normal.length() > 0.0 { normal.normalize() } else { normal });
+            }
+            "f" => {
+                if words.len() < 3 { return Err(format!("line {line_nr}: a face needs at least 3 corners")); }
+                let mut indexes = Vec::with_capacity(words.len());
+                for word in words.iter() {
+                    let mut parts = word.split('/');
+                    let position = resolve_index(parts.next(), positions.len(), line_nr)?
+                        .ok_or(format!("line {line_nr}: \"{word}\" has no position"))?;
+                    let uv = resolve_index(parts.next(), uvs.len(), line_nr)?;
+                    let normal = resolve_index(parts.next(), normals.len(), line_nr)?;
+                    let corner = (position, uv, normal);
+                    let index = *vertex_indexes.entry(corner).or_insert_with(|| {
+                        corners.push(corner);
+                        corners.len() - 1
+                    });
+                    indexes.push(index);
+                }
+                faces.push(Face::new(indexes));
+            }
+            "mtllib" => material_libraries.extend(words.iter().map(|word| word.to_string())),
+            "usemtl" => {
+                let name = words.join(" ");
+                if !materials.contains(&name) { materials.push(name); }
+            }
+            _ => {}
+        }
+    }
+
+    let mut mesh = Mesh::new(corners.iter().map(|&(position, _, _)| positions[position]).collect(), faces);
+    if corners.iter().all(|(_, uv, _)| uv.is_some()) {
+        mesh.attributes.uvs = corners.iter().map(|(_, uv, _)| uvs[uv.unwrap()]).collect();
+    }
+    if corners.iter().all(|(_, _, normal)| normal.is_some()) {
+        mesh.attributes.normals = corners.iter().map(|(_, _, normal)| normals[normal.unwrap()]).collect();
+    }
+    Ok(Obj{mesh, material_libraries, materials})
+}
+
+/// The index in a list of `amt` elements an obj index points to, None when it is left out
+fn resolve_index(index: Option<&str>, amt: usize, line_nr: usize) -> Result<Option<usize>, String> {
+    let Some(index) = index.filter(|index| !index.is_empty()) else { return Ok(None) };
+    let i: i64 = index.parse().map_err(|_| format!("line {line_nr}: \"{index}\" isn't an index"))?;
+    let resolved = if i > 0 { i - 1 } else { amt as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= amt as i64 {
+        return Err(format!("line {line_nr}: index {i} points past the {amt} elements read so far"));
+    }
+    Ok(Some(resolved as usize))
+}
+
+/// Parses the materials of an mtl file by name. Colors written as a single number are gray,
+/// colors given as `spectral` or `xyz` aren't supported.
+pub fn parse_mtl(text: &str) -> Result<HashMap<String, Material>, String> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+
+    for (line_i, line) in text.lines().enumerate() {
+        let line_nr = line_i + 1;
+        let line = line.split('#').next().unwrap();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else { continue };
+        let words: Vec<&str> = words.collect();
+
+        if keyword == "newmtl" {
+            materials.extend(current.take());
+            current = Some((words.join(" "), Material::default()));
+            continue;
+        }
+        let Some((_, material)) = current.as_mut() else { continue };
+        let numbers = words.iter()
+            .map(|word| word.parse::<f32>().map_err(|_| format!("line {line_nr}: \"{word}\" isn't a number")));
+        match keyword {
+            "Ka" | "Kd" | "Ks" => {
+                let c = numbers.collect::<Result<Vec<f32>, String>>()?;
+                let color = match c.as_slice() {
+                    [gray] => Color::new(*gray, *gray, *gray),
+                    [r, g, b, ..] => Color::new(*r, *g, *b),
+                    _ => return Err(format!("line {line_nr}: {keyword} needs 1 or 3 numbers")),
+                };
+                match keyword {
+                    "Ka" => material.ambient = color,
+                    "Kd" => material.diffuse = color,
+                    _ => material.specular = color,
+                }
+            }
+            "Ns" => {
+                material.shininess = numbers.take(1).next()
+                    .ok_or(format!("line {line_nr}: Ns needs a number"))??;
+            }
+            _ => {}
+        }
+    }
+    materials.extend(current);
+    Ok(materials)
+}
+
+/// Reads an obj file with the material of its first `usemtl`, looked up in the mtl files it refers to.
+/// A figure has a single material, so faces using other materials get it as well. Missing or broken
+/// mtl files and unknown materials are reported and leave the figure without a material.
+pub fn read_obj(path: &str) -> (Mesh, Option<Material>) {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("Failed to open obj file: {}", path));
+    let obj = parse_obj(&text).unwrap_or_else(|err| panic!("{path}: {err}"));
+
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut library = HashMap::new();
+    for material_library in obj.material_libraries.iter() {
+        let mtl_path = directory.join(material_library);
+        match fs::read_to_string(&mtl_path).map_err(|err| err.to_string()).and_then(|text| parse_mtl(&text)) {
+            Ok(materials) => library.extend(materials),
+            Err(err) => eprintln!("warning: {}: {err}", mtl_path.display()),
+        }
+    }
+
+    if obj.materials.len() > 1 {
+        eprintln!("warning: {path} uses {} materials, only the first one, {}, is used", obj.materials.len(), obj.materials[0]);
+    }
+    let material = obj.materials.first().and_then(|name| {
+        let material = library.get(name).copied();
+        if material.is_none() { eprintln!("warning: {path}: material {name} isn't in any of its mtl files"); }
+        material
+    });
+    (obj.mesh, material)
+}
+
+#[test]
+fn test_obj() {
+    // a unit square pyramid: its base a quad written with negative indexes, its sides sharing the
+    // position of the top but each with their own normal
+    let obj = parse_obj("# pyramid\nmtllib pyramid.mtl\n\
+        v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0.5 0.5 1\n\
+        vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 0.5\n\
+        vn 0 0 -1\nvn 0 -2 1\nvn 2 0 1\nvn 0 2 1\nvn -2 0 1\n\
+        usemtl stone\n\
+        f -5/-4/-5 -2/-1/-5 -3/-2/-5 -4/-3/-5\n\
+        f 1/1/2 2/2/2 5/5/2\nf 2/2/3 3/3/3 5/5/3\nf 3/3/4 4/4/4 5/5/4\nf 4/4/5 1/1/5 5/5/5\n").unwrap();
+    assert_eq!(obj.mesh.faces.len(), 5);
+    assert_eq!(obj.mesh.faces[0].indexes.len(), 4);
+    // the four base corners, the side corners with their own normals and the top once for every side
+    assert_eq!(obj.mesh.vertices.len(), 4 + 8 + 4);
+    assert_eq!(obj.mesh.attributes.uvs.len(), 16);
+    assert!((obj.mesh.attributes.normals[0].z() + 1.0).abs() < 1e-6);
+    assert!((obj.mesh.attributes.normals[4].length() - 1.0).abs() < 1e-6);
+    assert_eq!(obj.mesh.vertices[1], Vec4::new_point(0.0, 1.0, 0.0));
+    assert!((obj.mesh.volume() - 1.0 / 3.0).abs() < 1e-5);
+    assert_eq!(obj.material_libraries, vec!["pyramid.mtl"]);
+    assert_eq!(obj.materials, vec!["stone"]);
+
+    // texture coordinates on only some corners are dropped, the corners they set apart stay apart
+    let obj = parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2 3\nf 3 2 1\n").unwrap();
+    assert!(obj.mesh.attributes.uvs.is_empty());
+    assert_eq!(obj.mesh.vertices.len(), 4);
+    assert!(parse_obj("v 0 0 0\nf 1 2 3\n").is_err());
+    assert!(parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n").is_err());
+
+    let materials = parse_mtl("newmtl stone\nKa 0.1 0.2 0.3\nKd 0.5\nNs 32\n\nnewmtl moss\nKs 0 1 0\n").unwrap();
+    let stone = materials["stone"];
+    assert!((stone.ambient.g() - 0.2).abs() < 1e-6 && (stone.diffuse.b() - 0.5).abs() < 1e-6);
+    assert!((stone.specular.r() - 1.0).abs() < 1e-6 && stone.shininess == 32.0);
+    assert!((materials["moss"].specular.r()).abs() < 1e-6 && (materials["moss"].ambient.r() - 0.2).abs() < 1e-6);
+}
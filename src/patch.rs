@@ -0,0 +1,177 @@
+use std::fs;
+use crate::vec4::Vec4;
+
+/// A Bézier patch of any degree, `degree_v + 1` rows of `degree_u + 1` control points
+pub struct BezierPatch {
+    pub degree_u: usize,
+    pub degree_v: usize,
+    pub control_points: Vec<Vec4>, // row by row, control_points[v * (degree_u + 1) + u]
+}
+
+/// A uniform cubic B-spline surface over a net of `rows` by `cols` control points.
+/// Every 4 by 4 window of the net gives a span of the surface.
+pub struct BSplineSurface {
+    pub rows: usize,
+    pub cols: usize,
+    pub control_points: Vec<Vec4>, // row by row, control_points[row * cols + col]
+}
+
+/// A parametric surface evaluated to its position and derivatives along u and v
+pub trait ParametricSurface {
+    /// Parameter ranges along u and v
+    fn domain(&self) -> (f32, f32);
+    fn evaluate(&self, u: f32, v: f32) -> (Vec4, Vec4, Vec4); // position, d/du, d/dv
+}
+
+impl BezierPatch {
+    pub fn new_bicubic(control_points: Vec<Vec4>) -> Self {
+        Self{degree_u: 3, degree_v: 3, control_points}
+    }
+}
+
+impl ParametricSurface for BezierPatch {
+    fn domain(&self) -> (f32, f32) {
+        (1.0, 1.0)
+    }
+
+    fn evaluate(&self, u: f32, v: f32) -> (Vec4, Vec4, Vec4) {
+        let row_len = self.degree_u + 1;
+        weighted_sums(&self.control_points, row_len, (0, 0), &bernstein(self.degree_u, u), &bernstein(self.degree_v, v))
+    }
+}
+
+impl ParametricSurface for BSplineSurface {
+    fn domain(&self) -> (f32, f32) {
+        ((self.cols - 3) as f32, (self.rows - 3) as f32)
+    }
+
+    fn evaluate(&self, u: f32, v: f32) -> (Vec4, Vec4, Vec4) {
+        // the span the parameters lie in and the local parameter within that span
+        let span_u = (u.floor() as usize).min(self.cols - 4);
+        let span_v = (v.floor() as usize).min(self.rows - 4);
+        let basis_u = cubic_b_spline(u - span_u as f32);
+        let basis_v = cubic_b_spline(v - span_v as f32);
+        weighted_sums(&self.control_points, self.cols, (span_u, span_v), &basis_u, &basis_v)
+    }
+}
+
+/// Sums the control points of the window starting at `first` in the net, weighted by the
+/// basis functions along u and v and their derivatives
+fn weighted_sums(control_points: &[Vec4], row_len: usize, first: (usize, usize),
+                 (basis_u, derivative_u): &(Vec<f32>, Vec<f32>),
+                 (basis_v, derivative_v): &(Vec<f32>, Vec<f32>)) -> (Vec4, Vec4, Vec4) {
+    let (first_u, first_v) = first;
+    let zero = Vec4::new_vec4(0.0, 0.0, 0.0);
+    let (mut position, mut du, mut dv) = (zero, zero, zero);
+
+    for (j, (bv, dbv)) in basis_v.iter().zip(derivative_v.iter()).enumerate() {
+        for (i, (bu, dbu)) in basis_u.iter().zip(derivative_u.iter()).enumerate() {
+            let p = control_points[(first_v + j) * row_len + first_u + i];
+            let p = Vec4::new_vec4(p.x(), p.y(), p.z());
+            position = position.add(&p.scale(bu * bv));
+            du = du.add(&p.scale(dbu * bv));
+            dv = dv.add(&p.scale(bu * dbv));
+        }
+    }
+
+    (Vec4::new_point(position.x(), position.y(), position.z()), du, dv)
+}
+
+/// The Bernstein polynomials of the degree at t and their derivatives
+fn bernstein(degree: usize, t: f32) -> (Vec<f32>, Vec<f32>) {
+    let binomial = |n: usize, k: usize| (0..k).fold(1.0, |acc, i| acc * (n - i) as f32 / (i + 1) as f32);
+    let power = |base: f32, exp: i32| if exp < 0 { 0.0 } else { base.powi(exp) };
+    let n = degree as i32;
+
+    let basis = (0..=degree)
+        .map(|k| binomial(degree, k) * power(t, k as i32) * power(1.0 - t, n - k as i32))
+        .collect();
+    let derivative = (0..=degree)
+        .map(|k| {
+            let k = k as i32;
+            binomial(degree, k as usize) * (k as f32 * power(t, k - 1) * power(1.0 - t, n - k)
+                - (n - k) as f32 * power(t, k) * power(1.0 - t, n - k - 1))
+        })
+        .collect();
+
+    (basis, derivative)
+}
+
+/// The four uniform cubic B-spline basis functions at t in [0, 1] and their derivatives
+fn cubic_b_spline(t: f32) -> (Vec<f32>, Vec<f32>) {
+    let s = 1.0 - t;
+    let basis = vec![
+        s*s*s / 6.0,
+        (3.0*t*t*t - 6.0*t*t + 4.0) / 6.0,
+        (-3.0*t*t*t + 3.0*t*t + 3.0*t + 1.0) / 6.0,
+        t*t*t / 6.0,
+    ];
+    let derivative = vec![
+        -s*s / 2.0,
+        (3.0*t*t - 4.0*t) / 2.0,
+        (-3.0*t*t + 2.0*t + 1.0) / 2.0,
+        t*t / 2.0,
+    ];
+    (basis, derivative)
+}
+
+fn read_numbers(path: &str) -> Vec<f32> {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("Failed to open patch file: {}", path));
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f32>().unwrap_or_else(|_| panic!("patch file {path} contains a non number: {s}")))
+        .collect()
+}
+
+fn numbers_to_points(numbers: &[f32]) -> Vec<Vec4> {
+    numbers.chunks(3).map(|p| Vec4::new_point(p[0], p[1], p[2])).collect()
+}
+
+/// Reads Bézier patches in the classic `.bpt` layout: the number of patches, followed by
+/// the degree along u and v of every patch and its control points, row by row.
+pub fn read_bezier_patches(path: &str) -> Vec<BezierPatch> {
+    let numbers = read_numbers(path);
+    let patches_amt = numbers[0] as usize;
+
+    let mut patches = Vec::with_capacity(patches_amt);
+    let mut pos = 1;
+    for _ in 0..patches_amt {
+        let (degree_u, degree_v) = (numbers[pos] as usize, numbers[pos+1] as usize);
+        pos += 2;
+        let points_amt = (degree_u + 1) * (degree_v + 1);
+        let control_points = numbers_to_points(&numbers[pos..pos + 3*points_amt]);
+        pos += 3*points_amt;
+        patches.push(BezierPatch{degree_u, degree_v, control_points});
+    }
+
+    patches
+}
+
+/// Reads a B-spline control net: the number of rows and columns followed by the control points, row by row
+pub fn read_b_spline_surface(path: &str) -> BSplineSurface {
+    let numbers = read_numbers(path);
+    let (rows, cols) = (numbers[0] as usize, numbers[1] as usize);
+    if rows < 4 || cols < 4 {
+        panic!("a cubic B-spline net needs at least 4 by 4 control points, {path} has {rows} by {cols}");
+    }
+    let control_points = numbers_to_points(&numbers[2..2 + 3*rows*cols]);
+    BSplineSurface{rows, cols, control_points}
+}
+
+#[test]
+fn test_patches() {
+    // a flat bicubic patch over [0,3]x[0,3] is the identity map scaled by 3
+    let flat: Vec<Vec4> = (0..16).map(|i| Vec4::new_point((i % 4) as f32, (i / 4) as f32, 0.0)).collect();
+    let (p, du, dv) = BezierPatch::new_bicubic(flat.clone()).evaluate(0.25, 0.5);
+    assert!((p.x() - 0.75).abs() < 1e-5 && (p.y() - 1.5).abs() < 1e-5);
+    assert!((du.x() - 3.0).abs() < 1e-5 && du.y().abs() < 1e-5);
+    assert!((dv.y() - 3.0).abs() < 1e-5 && dv.x().abs() < 1e-5);
+
+    // a uniform B-spline reproduces straight lines, one span per parameter unit
+    let surface = BSplineSurface{rows: 4, cols: 4, control_points: flat};
+    assert_eq!(surface.domain(), (1.0, 1.0));
+    let (p, du, _) = surface.evaluate(0.5, 0.0);
+    assert!((p.x() - 1.5).abs() < 1e-5 && (p.y() - 1.0).abs() < 1e-5);
+    assert!((du.x() - 1.0).abs() < 1e-5);
+}
@@ -0,0 +1,15 @@
+use crate::vec4::Vec4;
+
+/// A point or vector in double precision, for the geometry that needs more than f32 to stay exact
+pub type Point = [f64; 3];
+
+pub fn from_vec4(v: &Vec4) -> Point { [v.x() as f64, v.y() as f64, v.z() as f64] }
+
+pub fn add(a: &Point, b: &Point) -> Point { [a[0] + b[0], a[1] + b[1], a[2] + b[2]] }
+pub fn sub(a: &Point, b: &Point) -> Point { [a[0] - b[0], a[1] - b[1], a[2] - b[2]] }
+pub fn scale(a: &Point, factor: f64) -> Point { [a[0] * factor, a[1] * factor, a[2] * factor] }
+pub fn dot(a: &Point, b: &Point) -> f64 { a[0] * b[0] + a[1] * b[1] + a[2] * b[2] }
+pub fn length(a: &Point) -> f64 { dot(a, a).sqrt() }
+pub fn cross(a: &Point, b: &Point) -> Point {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use crate::vec2::Vec2;
+use crate::vec4::Vec4;
+
+/// Parses a list of 2D points. Points are separated by newlines or semicolons,
+/// their coordinates by whitespace or commas, e.g. "0 0; 1 0.5; 1 2".
+pub fn parse_points_2d(text: &str) -> Vec<Vec2> {
+    parse_coordinates(text, 2).iter().map(|c| Vec2::new(c[0], c[1])).collect()
+}
+
+/// Parses a list of 3D points, written like the 2D points with a third coordinate
+pub fn parse_points_3d(text: &str) -> Vec<Vec4> {
+    parse_coordinates(text, 3).iter().map(|c| Vec4::new_point(c[0], c[1], c[2])).collect()
+}
+
+fn parse_coordinates(text: &str, dimensions: usize) -> Vec<Vec<f32>> {
+    let mut points = Vec::new();
+
+    for point in text.split(['\n', ';']) {
+        let coords: Vec<f32> = point
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f32>().expect("point coordinate must be a number"))
+            .collect();
+
+        if coords.is_empty() { continue; }
+        if coords.len() != dimensions {
+            eprintln!("{dimensions}D points must have exactly {dimensions} coordinates, got: {}", point.trim());
+            continue;
+        }
+        points.push(coords);
+    }
+
+    points
+}
+
+pub fn read_points_2d(path: &str) -> Vec<Vec2> {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("Failed to open points file: {}", path));
+    parse_points_2d(&text)
+}
+
+pub fn read_points_3d(path: &str) -> Vec<Vec4> {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("Failed to open points file: {}", path));
+    parse_points_3d(&text)
+}
+
+#[test]
+fn test_parse_points_2d() {
+    let points = parse_points_2d("0 0; 1, 0.5\n\n  1 2  \n");
+    assert_eq!(points, vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.5), Vec2::new(1.0, 2.0)]);
+}
+
+#[test]
+fn test_parse_points_3d() {
+    let points = parse_points_3d("0 0 1\n1, 2, 3\n4 5\n");
+    assert_eq!(points, vec![Vec4::new_point(0.0, 0.0, 1.0), Vec4::new_point(1.0, 2.0, 3.0)]);
+}
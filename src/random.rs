@@ -0,0 +1,40 @@
+/// Small seeded pseudo random generator (xorshift64*), so generated figures
+/// look the same every time a scene is rendered.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // the state of xorshift must never be zero
+        Self{state: seed.wrapping_mul(0x9E3779B97F4A7C15) | 1}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Uniformly distributed in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniformly distributed in [min, max)
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+#[test]
+fn test_random() {
+    let mut a = Random::new(42);
+    let mut b = Random::new(42);
+    for _ in 0..100 {
+        let value = a.next_f32();
+        assert_eq!(value, b.next_f32());
+        assert!((0.0..1.0).contains(&value));
+    }
+}
@@ -0,0 +1,286 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use crate::attributes::VertexAttributes;
+use crate::figure::{Face, Mesh};
+use crate::vec4::Vec4;
+use crate::point3::{self, Point, add, sub, scale, dot, length, cross};
+
+/// How much heavier the planes that keep boundaries in place count than the planes of the faces
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+/// The symmetric matrix of the sum of squared distances to a set of planes,
+/// stored as a², ab, ac, ad, b², bc, bd, c², cd, d² for planes ax + by + cz + d = 0
+#[derive(Clone, Copy)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn new_plane(normal: &Point, point: &Point, weight: f64) -> Self {
+        let [a, b, c] = *normal;
+        let d = -dot(normal, point);
+        Self([a*a, a*b, a*c, a*d, b*b, b*c, b*d, c*c, c*d, d*d].map(|q| q * weight))
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut sum = self.0;
+        for (s, o) in sum.iter_mut().zip(other.0.iter()) { *s += o; }
+        Self(sum)
+    }
+
+    fn error(&self, p: &Point) -> f64 {
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, d2] = self.0;
+        let [x, y, z] = *p;
+        a2*x*x + 2.0*ab*x*y + 2.0*ac*x*z + 2.0*ad*x + b2*y*y + 2.0*bc*y*z + 2.0*bd*y + c2*z*z + 2.0*cd*z + d2
+    }
+
+    /// The point with the smallest error, if there is a single one
+    fn minimum(&self) -> Option<Point> {
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, _] = self.0;
+        let det = a2 * (b2*c2 - bc*bc) - ab * (ab*c2 - bc*ac) + ac * (ab*bc - b2*ac);
+        let scale = (a2 + b2 + c2).powi(3);
+        if scale <= 0.0 || det.abs() <= 1e-9 * scale { return None; }
+        // Cramer's rule on the gradient being zero
+        let rhs = [-ad, -bd, -cd];
+        let x = (rhs[0] * (b2*c2 - bc*bc) - ab * (rhs[1]*c2 - bc*rhs[2]) + ac * (rhs[1]*bc - b2*rhs[2])) / det;
+        let y = (a2 * (rhs[1]*c2 - bc*rhs[2]) - rhs[0] * (ab*c2 - bc*ac) + ac * (ab*rhs[2] - rhs[1]*ac)) / det;
+        let z = (a2 * (b2*rhs[2] - rhs[1]*bc) - ab * (ab*rhs[2] - rhs[1]*ac) + rhs[0] * (ab*bc - b2*ac)) / det;
+        Some([x, y, z])
+    }
+}
+
+/// A possible collapse of edge (a, b) into one vertex at `position`, valid as long as neither vertex changed
+struct Collapse {
+    cost: f64,
+    a: usize,
+    b: usize,
+    versions: (u32, u32),
+    position: Point,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+impl Eq for Collapse {}
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl Ord for Collapse {
+    // the cheapest collapse comes first out of the max heap, ties are broken by the vertices
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then((other.a, other.b).cmp(&(self.a, self.b)))
+    }
+}
+
+struct Simplifier {
+    points: Vec<Point>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    vertex_faces: Vec<Vec<usize>>,
+    faces: Vec<[usize; 3]>,
+    face_alive: Vec<bool>,
+}
+
+/// Reduces a triangle mesh to at most `target_face_count` faces by collapsing the edges whose merged
+/// vertex lies closest to the planes of the faces around both ends (Garland and Heckbert). Boundary
+/// edges get extra planes perpendicular to their face so the outline keeps its shape. Collapses that
+/// would flip a face or pinch the mesh are skipped, so the result can have more faces than asked for.
+pub fn simplify(mesh: &Mesh, target_face_count: usize) -> Mesh {
+    let points: Vec<Point> = mesh.vertices.iter().map(point3::from_vec4).collect();
+    let faces: Vec<[usize; 3]> = mesh.faces.iter()
+        .filter(|face| face.indexes.len() == 3)
+        .map(|face| [face.indexes[0], face.indexes[1], face.indexes[2]])
+        .collect();
+
+    let mut quadrics = vec![Quadric([0.0; 10]); points.len()];
+    let mut vertex_faces = vec![Vec::new(); points.len()];
+    let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (face_i, face) in faces.iter().enumerate() {
+        let normal = cross(&sub(&points[face[1]], &points[face[0]]), &sub(&points[face[2]], &points[face[0]]));
+        let double_area = length(&normal);
+        for i in 0..3 {
+            vertex_faces[face[i]].push(face_i);
+            edge_faces.entry((face[i].min(face[(i+1)%3]), face[i].max(face[(i+1)%3]))).or_default().push(face_i);
+            if double_area > 0.0 {
+                let plane = Quadric::new_plane(&scale(&normal, 1.0 / double_area), &points[face[0]], double_area / 2.0);
+                quadrics[face[i]] = quadrics[face[i]].add(&plane);
+            }
+        }
+    }
+
+    let mut edges: Vec<(usize, usize)> = edge_faces.keys().copied().collect();
+    edges.sort();
+    for &(a, b) in edges.iter() {
+        let e_faces = &edge_faces[&(a, b)];
+        if e_faces.len() != 1 { continue; }
+        let face = faces[e_faces[0]];
+        let face_normal = cross(&sub(&points[face[1]], &points[face[0]]), &sub(&points[face[2]], &points[face[0]]));
+        let edge = sub(&points[b], &points[a]);
+        let normal = cross(&edge, &face_normal);
+        if length(&normal) <= 0.0 { continue; }
+        let plane = Quadric::new_plane(&scale(&normal, 1.0 / length(&normal)), &points[a], BOUNDARY_WEIGHT * dot(&edge, &edge));
+        quadrics[a] = quadrics[a].add(&plane);
+        quadrics[b] = quadrics[b].add(&plane);
+    }
+
+    let mut simplifier = Simplifier{
+        versions: vec![0; points.len()],
+        points, quadrics, vertex_faces,
+        face_alive: vec![true; faces.len()],
+        faces,
+    };
+
+    let mut heap = BinaryHeap::new();
+    for &(a, b) in edges.iter() {
+        heap.push(simplifier.collapse(a, b));
+    }
+
+    let mut faces_amt = simplifier.faces.len();
+    while faces_amt > target_face_count {
+        let Some(collapse) = heap.pop() else { break };
+        if (simplifier.versions[collapse.a], simplifier.versions[collapse.b]) != collapse.versions { continue; }
+        if !simplifier.can_collapse(&collapse) { continue; }
+
+        faces_amt -= simplifier.apply(&collapse);
+        let a = collapse.a;
+        let mut neighbours: Vec<usize> = simplifier.vertex_faces[a].iter()
+            .flat_map(|&face_i| simplifier.faces[face_i])
+            .filter(|&v| v != a)
+            .collect();
+        neighbours.sort();
+        neighbours.dedup();
+        for v in neighbours {
+            heap.push(simplifier.collapse(a.min(v), a.max(v)));
+        }
+    }
+
+    simplifier.into_mesh(&mesh.attributes)
+}
+
+impl Simplifier {
+    fn collapse(&self, a: usize, b: usize) -> Collapse {
+        let quadric = self.quadrics[a].add(&self.quadrics[b]);
+        let (pa, pb) = (self.points[a], self.points[b]);
+        let middle = scale(&add(&pa, &pb), 0.5);
+
+        // the optimal point is only trusted when it's close to the edge
+        let mut candidates = vec![pa, pb, middle];
+        if let Some(optimal) = quadric.minimum() {
+            if length(&sub(&optimal, &middle)) <= length(&sub(&pb, &pa)) {
+                candidates.insert(0, optimal);
+            }
+        }
+        let (cost, position) = candidates.into_iter()
+            .map(|p| (quadric.error(&p), p))
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .unwrap();
+
+        Collapse{cost, a, b, versions: (self.versions[a], self.versions[b]), position}
+    }
+
+    fn can_collapse(&self, collapse: &Collapse) -> bool {
+        let (a, b) = (collapse.a, collapse.b);
+        let neighbours = |v: usize| -> Vec<usize> {
+            let mut n: Vec<usize> = self.vertex_faces[v].iter().flat_map(|&f| self.faces[f]).filter(|&u| u != v).collect();
+            n.sort();
+            n.dedup();
+            n
+        };
+
+        // the neighbours both ends share must be exactly the third corners of the faces along the edge,
+        // otherwise the collapse glues two parts of the surface together
+        let shared_faces: Vec<usize> = self.vertex_faces[a].iter().copied()
+            .filter(|&f| self.faces[f].contains(&b))
+            .collect();
+        if shared_faces.is_empty() { return false; }
+        let mut opposite: Vec<usize> = shared_faces.iter()
+            .map(|&f| *self.faces[f].iter().find(|&&v| v != a && v != b).unwrap())
+            .collect();
+        opposite.sort();
+        let neighbours_b = neighbours(b);
+        let shared: Vec<usize> = neighbours(a).into_iter().filter(|v| neighbours_b.contains(v)).collect();
+        if shared != opposite { return false; }
+
+        // an edge across the inside may not join two boundaries
+        if shared_faces.len() > 1 && self.is_on_boundary(a) && self.is_on_boundary(b) { return false; }
+
+        // no face around either end may turn over or collapse to a sliver
+        for &v in [a, b].iter() {
+            for &face_i in self.vertex_faces[v].iter() {
+                let face = self.faces[face_i];
+                if face.contains(&a) && face.contains(&b) { continue; }
+                let corners = face.map(|u| self.points[u]);
+                let moved = face.map(|u| if u == v { collapse.position } else { self.points[u] });
+                let before = cross(&sub(&corners[1], &corners[0]), &sub(&corners[2], &corners[0]));
+                let after = cross(&sub(&moved[1], &moved[0]), &sub(&moved[2], &moved[0]));
+                if dot(&before, &after) <= 0.2 * length(&before) * length(&after) || length(&after) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn is_on_boundary(&self, v: usize) -> bool {
+        let mut edge_counts: HashMap<usize, u32> = HashMap::new();
+        for &face_i in self.vertex_faces[v].iter() {
+            for u in self.faces[face_i] {
+                if u != v { *edge_counts.entry(u).or_default() += 1; }
+            }
+        }
+        edge_counts.values().any(|&count| count == 1)
+    }
+
+    /// Merges b into a and returns how many faces disappeared
+    fn apply(&mut self, collapse: &Collapse) -> usize {
+        let (a, b) = (collapse.a, collapse.b);
+        self.points[a] = collapse.position;
+        self.quadrics[a] = self.quadrics[a].add(&self.quadrics[b]);
+        self.versions[a] += 1;
+        self.versions[b] += 1;
+
+        let mut removed = 0;
+        let b_faces = std::mem::take(&mut self.vertex_faces[b]);
+        for face_i in b_faces {
+            if self.faces[face_i].contains(&a) {
+                self.face_alive[face_i] = false;
+                removed += 1;
+                for v in self.faces[face_i] {
+                    self.vertex_faces[v].retain(|&f| f != face_i);
+                }
+            }
+            else {
+                for v in self.faces[face_i].iter_mut() {
+                    if *v == b { *v = a; }
+                }
+                self.vertex_faces[a].push(face_i);
+            }
+        }
+        removed
+    }
+
+    /// The vertices that are left keep their attributes, a collapse keeps those of the vertex it merges into.
+    /// Normals and tangents are dropped.
+    fn into_mesh(self, attributes: &VertexAttributes) -> Mesh {
+        let mut new_indexes = vec![usize::MAX; self.points.len()];
+        let mut vertices = Vec::new();
+        let mut sources = Vec::new();
+        let mut faces = Vec::new();
+        for (face, _) in self.faces.iter().zip(self.face_alive.iter()).filter(|(_, &alive)| alive) {
+            faces.push(Face::new(face.iter().map(|&v| {
+                if new_indexes[v] == usize::MAX {
+                    new_indexes[v] = vertices.len();
+                    sources.push(v);
+                    let p = self.points[v];
+                    vertices.push(Vec4::new_point(p[0] as f32, p[1] as f32, p[2] as f32));
+                }
+                new_indexes[v]
+            }).collect()));
+        }
+        let mut mesh = Mesh::new(vertices, faces);
+        mesh.attributes = attributes.select(&sources);
+        // the collapses moved the surface, the old normals and tangents don't fit it anymore
+        mesh.attributes.normals.clear();
+        mesh.attributes.tangents.clear();
+        mesh
+    }
+}
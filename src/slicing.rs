@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use crate::bounds::Aabb;
+use crate::figure::Mesh;
+use crate::vec4::Vec4;
+
+/// The points p with normal . p == offset
+#[derive(Clone, Copy, Debug)]
+pub struct Plane {
+    pub normal: Vec4,
+    pub offset: f32,
+}
+
+impl Plane {
+    pub fn new(point: &Vec4, normal: &Vec4) -> Self {
+        let normal = Vec4::new_vec4(normal.x(), normal.y(), normal.z()).normalize();
+        Self{normal, offset: normal.dot(point)}
+    }
+
+    /// Positive in front of the plane, on the side its normal points to
+    pub fn distance(&self, p: &Vec4) -> f32 {
+        self.normal.dot(p) - self.offset
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Polyline {
+    pub points: Vec<Vec4>,
+    pub closed: bool, // the last point connects back to the first
+}
+
+/// The edge a crossing lies on, its two vertex indexes with the smallest first
+type Edge = (usize, usize);
+
+/// Cuts the triangles by the plane and chains the pieces of cut into polylines. The pieces are
+/// joined through the edges they cross, so they only connect where the triangles share vertices.
+/// On a closed, consistently wound mesh every polyline is a closed loop, outer loops run counter
+/// clock wise seen from the side the normal points to and the loops around holes clock wise.
+/// Vertices exactly in the plane count as lying in front of it, so a cut never runs through them.
+pub fn slice(mesh: &Mesh, plane: &Plane) -> Vec<Polyline> {
+    let in_front: Vec<bool> = mesh.vertices.iter().map(|v| plane.distance(v) >= 0.0).collect();
+    let crossing = |(a, b): Edge| {
+        let (p, q) = (mesh.vertices[a], mesh.vertices[b]);
+        let t = plane.distance(&p) / (plane.distance(&p) - plane.distance(&q));
+        p.add(&q.sub(&p).scale(t))
+    };
+
+    // every cut through a triangle runs from the edge it enters the triangle through to the one it leaves by
+    let mut cuts: HashMap<Edge, Vec<Edge>> = HashMap::new();
+    let mut entered: HashMap<Edge, u32> = HashMap::new();
+    for face in mesh.faces.iter() {
+        let n = face.indexes.len();
+        let (mut from, mut to) = (None, None);
+        for i in 0..n {
+            let (a, b) = (face.indexes[i], face.indexes[(i+1)%n]);
+            if in_front[a] == in_front[b] { continue; }
+            let edge = (a.min(b), a.max(b));
+            if in_front[a] { from = Some(edge) } else { to = Some(edge) }
+        }
+        if let (Some(from), Some(to)) = (from, to) {
+            cuts.entry(from).or_default().push(to);
+            *entered.entry(to).or_default() += 1;
+        }
+    }
+
+    // open polylines start where nothing leads in, what remains afterwards are loops
+    let mut starts: Vec<Edge> = cuts.keys().filter(|edge| !entered.contains_key(edge)).copied().collect();
+    starts.sort();
+    let mut loop_starts: Vec<Edge> = cuts.keys().copied().collect();
+    loop_starts.sort();
+    starts.extend(loop_starts);
+
+    let mut polylines = Vec::new();
+    for start in starts {
+        if cuts.get(&start).is_none_or(|next| next.is_empty()) { continue; }
+        let mut points = vec![crossing(start)];
+        let mut current = start;
+        let mut closed = false;
+        while let Some(next) = cuts.get_mut(&current).and_then(|next| next.pop()) {
+            if next == start {
+                closed = true;
+                break;
+            }
+            points.push(crossing(next));
+            current = next;
+        }
+        // cuts through a vertex in the plane cross all of its edges there
+        points.dedup_by(|a, b| a.sub(b).length() < 1e-6);
+        if closed && points.len() > 1 && points[0].sub(&points[points.len()-1]).length() < 1e-6 {
+            points.pop();
+        }
+        polylines.push(Polyline{points, closed});
+    }
+    polylines
+}
+
+/// The cross sections of all meshes at `layers_amt` heights evenly spread through the box around them,
+/// each layer half a layer away from the bottom and top. Paired with the height of the layer.
+pub fn slice_stack(meshes: &[Mesh], layers_amt: u32) -> Vec<(f32, Vec<Polyline>)> {
+    let Some(bounds) = meshes.iter().filter_map(|mesh| mesh.aabb()).reduce(|a, b| a.union(&b)) else {
+        return Vec::new();
+    };
+    let layer_height = bounds.size().z() / layers_amt as f32;
+    (0..layers_amt).map(|i| {
+        let z = bounds.min.z() + (i as f32 + 0.5) * layer_height;
+        let plane = Plane::new(&Vec4::new_point(0.0, 0.0, z), &Vec4::new_vec4(0.0, 0.0, 1.0));
+        (z, meshes.iter().flat_map(|mesh| mesh.slice(&plane)).collect())
+    }).collect()
+}
+
+/// Every layer as a line "layer <i> z <height>" followed by its polylines, one per line as
+/// "closed" or "open" and the x and y of its points like "0 0; 1 0.5; 1 2"
+pub fn to_text(layers: &[(f32, Vec<Polyline>)]) -> String {
+    let mut text = String::new();
+    for (layer_i, (z, polylines)) in layers.iter().enumerate() {
+        text += &format!("layer {layer_i} z {z}\n");
+        for polyline in polylines.iter() {
+            let points: Vec<String> = polyline.points.iter().map(|p| format!("{} {}", p.x(), p.y())).collect();
+            text += &format!("{} {}\n", if polyline.closed { "closed" } else { "open" }, points.join("; "));
+        }
+    }
+    text
+}
+
+/// The layers side by side in rows, seen from above, every layer in a cell as big as the box around all of them
+pub fn to_svg(layers: &[(f32, Vec<Polyline>)]) -> String {
+    let points: Vec<Vec4> = layers.iter().flat_map(|(_, polylines)| polylines.iter().flat_map(|p| p.points.iter().copied())).collect();
+    let Some(bounds) = Aabb::from_points(&points) else {
+        return "<svg xmlns=\"http://www.w3.org/2000/svg\"/>\n".to_string();
+    };
+    let columns = (layers.len() as f32).sqrt().ceil() as usize;
+    let rows = layers.len().div_ceil(columns);
+    let margin = 0.1 * bounds.size().x().max(bounds.size().y()).max(f32::MIN_POSITIVE);
+    let (cell_width, cell_height) = (bounds.size().x() + 2.0 * margin, bounds.size().y() + 2.0 * margin);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">\n",
+        cell_width * columns as f32, cell_height * rows as f32,
+    );
+    for (layer_i, (z, polylines)) in layers.iter().enumerate() {
+        let (left, top) = ((layer_i % columns) as f32 * cell_width, (layer_i / columns) as f32 * cell_height);
+        svg += &format!("  <g id=\"layer{layer_i}\" data-z=\"{z}\">\n");
+        for polyline in polylines.iter() {
+            // svg's y axis points down
+            let points: Vec<String> = polyline.points.iter()
+                .map(|p| format!("{},{}", left + margin + p.x() - bounds.min.x(), top + margin + bounds.max.y() - p.y()))
+                .collect();
+            svg += &format!(
+                "    <{} points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"{}\"/>\n",
+                if polyline.closed { "polygon" } else { "polyline" }, points.join(" "), margin * 0.05,
+            );
+        }
+        svg += "  </g>\n";
+    }
+    svg += "</svg>\n";
+    svg
+}
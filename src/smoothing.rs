@@ -0,0 +1,41 @@
+use crate::figure::Mesh;
+use crate::half_edge::HalfEdgeMesh;
+use crate::vec4::Vec4;
+
+/// The neighbours every vertex is pulled toward. Vertices on a boundary only follow their two
+/// neighbours along it, so holes keep their shape instead of being pulled into the surface. Fails for
+/// meshes the half edges can't describe.
+fn neighbours(mesh: &Mesh, pin_boundary: bool) -> Result<Vec<Vec<usize>>, String> {
+    let half_edge_mesh = HalfEdgeMesh::from_mesh(mesh)?;
+    // the vertices around a hole follow each other in its loop
+    let mut boundary_neighbours = vec![Vec::new(); mesh.vertices.len()];
+    for hole in half_edge_mesh.boundary_loops() {
+        let n = hole.len();
+        for i in 0..n {
+            boundary_neighbours[hole[i]] = vec![hole[(i+n-1)%n], hole[(i+1)%n]];
+        }
+    }
+    Ok(boundary_neighbours.into_iter().enumerate().map(|(v, boundary)| {
+        if !half_edge_mesh.is_boundary_vertex(v) { half_edge_mesh.one_ring(v).collect() }
+        else if pin_boundary { Vec::new() }
+        else { boundary }
+    }).collect())
+}
+
+/// Moves every vertex `factor` of the way toward the average of its neighbours, once for every factor.
+/// Leaves the mesh as it is when it has an edge of more than two faces, faces that don't agree on which
+/// way they point, or surfaces touching in a single vertex.
+pub fn smooth(mesh: &mut Mesh, factors: impl Iterator<Item = f32>, pin_boundary: bool) -> Result<(), String> {
+    let neighbours = neighbours(mesh, pin_boundary).map_err(|err| format!("can't smooth the mesh, {err}"))?;
+    for factor in factors {
+        let vertices: Vec<Vec4> = mesh.vertices.iter().enumerate().map(|(i, p)| {
+            if neighbours[i].is_empty() { return *p; }
+            let average = neighbours[i].iter()
+                .fold(Vec4::new_vec4(0.0, 0.0, 0.0), |acc, &j| acc.add(&mesh.vertices[j].sub(p)))
+                .scale(1.0 / neighbours[i].len() as f32);
+            p.add(&average.scale(factor))
+        }).collect();
+        mesh.vertices = vertices;
+    }
+    Ok(())
+}
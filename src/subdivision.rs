@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+use crate::figure::{Face, Mesh};
+use crate::vec4::Vec4;
+
+/// An edge as its two vertex indexes, the smallest first
+type Edge = (usize, usize);
+
+fn edge(a: usize, b: usize) -> Edge {
+    (a.min(b), a.max(b))
+}
+
+/// The faces along every edge and which edges are sharp: boundaries, creases and edges of more than two faces
+struct Edges {
+    faces: HashMap<Edge, Vec<usize>>,
+    sharp: HashSet<Edge>,
+}
+
+impl Edges {
+    fn new(mesh: &Mesh) -> Self {
+        let mut faces: HashMap<Edge, Vec<usize>> = HashMap::new();
+        for (face_i, face) in mesh.faces.iter().enumerate() {
+            let n = face.indexes.len();
+            for i in 0..n {
+                faces.entry(edge(face.indexes[i], face.indexes[(i+1)%n])).or_default().push(face_i);
+            }
+        }
+        let creases: HashSet<Edge> = mesh.creases.iter().map(|&(a, b)| edge(a, b)).collect();
+        let sharp = faces.iter()
+            .filter(|(e, e_faces)| e_faces.len() != 2 || creases.contains(e))
+            .map(|(e, _)| *e)
+            .collect();
+        Self{faces, sharp}
+    }
+
+    /// The neighbours of every vertex and the neighbours along sharp edges
+    fn neighbours(&self, vertices_amt: usize) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut all = vec![Vec::new(); vertices_amt];
+        let mut sharp = vec![Vec::new(); vertices_amt];
+        // sorted so the sums come out the same on every run
+        let mut edges: Vec<&Edge> = self.faces.keys().collect();
+        edges.sort();
+        for &(a, b) in edges {
+            all[a].push(b);
+            all[b].push(a);
+            if self.sharp.contains(&(a, b)) {
+                sharp[a].push(b);
+                sharp[b].push(a);
+            }
+        }
+        (all, sharp)
+    }
+}
+
+fn sum(points: impl Iterator<Item = Vec4>) -> Vec4 {
+    points.fold(Vec4::new_vec4(0.0, 0.0, 0.0), |acc, p| acc.add(&Vec4::new_vec4(p.x(), p.y(), p.z())))
+}
+
+fn to_point(v: &Vec4) -> Vec4 {
+    Vec4::new_point(v.x(), v.y(), v.z())
+}
+
+/// Where a vertex along sharp edges moves to: between its two sharp neighbours like on a curve,
+/// a corner where more sharp edges meet stays put
+fn sharp_vertex(p: &Vec4, sharp_neighbours: &[Vec4]) -> Vec4 {
+    if sharp_neighbours.len() == 2 {
+        to_point(&p.scale(0.75).add(&sum(sharp_neighbours.iter().copied()).scale(0.125)))
+    }
+    else {
+        *p
+    }
+}
+
+/// What the attributes of the old vertices are made of: themselves, they only move. The attributes of
+/// the new points are blended from the vertices around them, without the smoothing of the positions.
+fn old_vertex_sources(mesh: &Mesh) -> Vec<Vec<(usize, f32)>> {
+    (0..mesh.vertices.len()).map(|i| vec![(i, 1.0)]).collect()
+}
+
+/// The sharp edges split in two halves around the new points on them
+fn split_creases(mesh: &Mesh, edge_points: &HashMap<Edge, usize>) -> Vec<(usize, usize)> {
+    mesh.creases.iter()
+        .filter_map(|&(a, b)| edge_points.get(&edge(a, b)).map(|&middle| [(a, middle), (middle, b)]))
+        .flatten()
+        .collect()
+}
+
+/// One step of Loop subdivision on a mesh of triangles. Every triangle is split in four, the old
+/// vertices keep their indexes and come first.
+pub fn loop_step(mesh: &Mesh) -> Mesh {
+    let edges = Edges::new(mesh);
+    let (neighbours, sharp_neighbours) = edges.neighbours(mesh.vertices.len());
+
+    let mut vertices: Vec<Vec4> = mesh.vertices.iter().enumerate().map(|(i, p)| {
+        let n = neighbours[i].len();
+        if sharp_neighbours[i].len() >= 2 {
+            let sharp: Vec<Vec4> = sharp_neighbours[i].iter().map(|&j| mesh.vertices[j]).collect();
+            return sharp_vertex(p, &sharp);
+        }
+        if n < 3 { return *p; }
+        let beta_term = 3.0/8.0 + 0.25 * (2.0*PI / n as f32).cos();
+        let beta = (5.0/8.0 - beta_term * beta_term) / n as f32;
+        let around = sum(neighbours[i].iter().map(|&j| mesh.vertices[j]));
+        to_point(&p.scale(1.0 - n as f32 * beta).add(&around.scale(beta)))
+    }).collect();
+
+    let mut sorted_edges: Vec<(&Edge, &Vec<usize>)> = edges.faces.iter().collect();
+    sorted_edges.sort_by_key(|(e, _)| **e);
+    let mut edge_points = HashMap::new();
+    let mut sources = old_vertex_sources(mesh);
+    for (&(a, b), e_faces) in sorted_edges {
+        let ends = mesh.vertices[a].add(&mesh.vertices[b]);
+        let point = if edges.sharp.contains(&(a, b)) {
+            ends.scale(0.5)
+        }
+        else {
+            // the corners of both triangles across the edge
+            let opposite = sum(e_faces.iter().map(|&face_i| {
+                let indexes = &mesh.faces[face_i].indexes;
+                let corner = *indexes.iter().find(|&&i| i != a && i != b).unwrap_or(&a);
+                mesh.vertices[corner]
+            }));
+            ends.scale(3.0/8.0).add(&opposite.scale(1.0/8.0))
+        };
+        edge_points.insert((a, b), vertices.len());
+        vertices.push(to_point(&point));
+        sources.push(vec![(a, 0.5), (b, 0.5)]);
+    }
+
+    let mut faces = Vec::with_capacity(mesh.faces.len() * 4);
+    for face in mesh.faces.iter() {
+        let [a, b, c] = [face.indexes[0], face.indexes[1], face.indexes[2]];
+        let (ab, bc, ca) = (edge_points[&edge(a, b)], edge_points[&edge(b, c)], edge_points[&edge(c, a)]);
+        faces.push(Face::new(vec![a, ab, ca]));
+        faces.push(Face::new(vec![b, bc, ab]));
+        faces.push(Face::new(vec![c, ca, bc]));
+        faces.push(Face::new(vec![ab, bc, ca]));
+    }
+
+    let mut subdivided = Mesh::new(vertices, faces);
+    subdivided.attributes = mesh.attributes.blend(&sources);
+    // the surface is smoothed, the old normals don't fit it anymore
+    subdivided.attributes.normals.clear();
+    subdivided.creases = split_creases(mesh, &edge_points);
+    subdivided
+}
+
+/// One step of Catmull-Clark subdivision. Every face with n corners is split in n quads around its
+/// center, the old vertices keep their indexes and are followed by the edge points and face points.
+pub fn catmull_clark_step(mesh: &Mesh) -> Mesh {
+    let edges = Edges::new(mesh);
+    let (neighbours, sharp_neighbours) = edges.neighbours(mesh.vertices.len());
+
+    let face_points: Vec<Vec4> = mesh.faces.iter()
+        .map(|face| to_point(&sum(face.indexes.iter().map(|&i| mesh.vertices[i])).scale(1.0 / face.indexes.len() as f32)))
+        .collect();
+
+    let mut vertex_faces = vec![Vec::new(); mesh.vertices.len()];
+    for (face_i, face) in mesh.faces.iter().enumerate() {
+        for &i in face.indexes.iter() {
+            vertex_faces[i].push(face_i);
+        }
+    }
+
+    let mut vertices: Vec<Vec4> = mesh.vertices.iter().enumerate().map(|(i, p)| {
+        let n = neighbours[i].len();
+        if sharp_neighbours[i].len() >= 2 {
+            let sharp: Vec<Vec4> = sharp_neighbours[i].iter().map(|&j| mesh.vertices[j]).collect();
+            return sharp_vertex(p, &sharp);
+        }
+        if n < 3 || vertex_faces[i].is_empty() { return *p; }
+        // (F + 2R + (n-3)P) / n with F the average face point and R the average edge middle
+        let f = sum(vertex_faces[i].iter().map(|&face_i| face_points[face_i])).scale(1.0 / vertex_faces[i].len() as f32);
+        let r = sum(neighbours[i].iter().map(|&j| mesh.vertices[j])).scale(1.0 / n as f32).add(p).scale(0.5);
+        to_point(&f.add(&r.scale(2.0)).add(&p.scale(n as f32 - 3.0)).scale(1.0 / n as f32))
+    }).collect();
+
+    let mut sorted_edges: Vec<(&Edge, &Vec<usize>)> = edges.faces.iter().collect();
+    sorted_edges.sort_by_key(|(e, _)| **e);
+    let mut edge_points = HashMap::new();
+    let mut sources = old_vertex_sources(mesh);
+    for (&(a, b), e_faces) in sorted_edges {
+        let ends = mesh.vertices[a].add(&mesh.vertices[b]);
+        let point = if edges.sharp.contains(&(a, b)) {
+            ends.scale(0.5)
+        }
+        else {
+            ends.add(&sum(e_faces.iter().map(|&face_i| face_points[face_i]))).scale(0.25)
+        };
+        edge_points.insert((a, b), vertices.len());
+        vertices.push(to_point(&point));
+        sources.push(vec![(a, 0.5), (b, 0.5)]);
+    }
+
+    let first_face_point = vertices.len();
+    vertices.extend(face_points);
+    sources.extend(mesh.faces.iter().map(|face| {
+        let weight = 1.0 / face.indexes.len() as f32;
+        face.indexes.iter().map(|&i| (i, weight)).collect()
+    }));
+
+    let mut faces = Vec::new();
+    for (face_i, face) in mesh.faces.iter().enumerate() {
+        let n = face.indexes.len();
+        for i in 0..n {
+            let (previous, current, next) = (face.indexes[(i+n-1)%n], face.indexes[i], face.indexes[(i+1)%n]);
+            faces.push(Face::new(vec![
+                current,
+                edge_points[&edge(current, next)],
+                first_face_point + face_i,
+                edge_points[&edge(previous, current)],
+            ]));
+        }
+    }
+
+    let mut subdivided = Mesh::new(vertices, faces);
+    subdivided.attributes = mesh.attributes.blend(&sources);
+    // the surface is smoothed, the old normals don't fit it anymore
+    subdivided.attributes.normals.clear();
+    subdivided.creases = split_creases(mesh, &edge_points);
+    subdivided
+}
@@ -0,0 +1,123 @@
+use crate::vec2::Vec2;
+use crate::vec4::Vec4;
+
+/// Splits a polygon into triangles of indexes into `points`, listed in the same winding as the polygon.
+/// Convex polygons are fanned, others are cut with ear clipping. Corners on a straight line between their
+/// neighbours are cut away without a triangle. Fails when the polygon has less than three corners, has no
+/// area or crosses itself.
+pub fn triangulate_polygon(points: &[Vec4]) -> Result<Vec<[usize; 3]>, ()> {
+    let n = points.len();
+    if n < 3 { return Err(()); }
+    if n == 3 { return Ok(vec![[0, 1, 2]]); }
+
+    // Newell's method gives the normal of the best fitting plane, its length is twice the area
+    let mut normal = Vec4::new_vec4(0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (p, q) = (points[i], points[(i+1)%n]);
+        normal = normal.add(&Vec4::new_vec4(
+            (p.y() - q.y()) * (p.z() + q.z()),
+            (p.z() - q.z()) * (p.x() + q.x()),
+            (p.x() - q.x()) * (p.y() + q.y()),
+        ));
+    }
+    let size = points.iter().skip(1).map(|p| p.sub(&points[0]).length()).fold(0.0, f32::max);
+    let eps = size * size * 1e-6;
+    if normal.length() <= eps { return Err(()); }
+    let normal = normal.normalize();
+
+    // project in the plane so the polygon is counter clock wise seen from the front
+    let helper = if normal.x().abs() < 0.9 { Vec4::new_vec4(1.0, 0.0, 0.0) } else { Vec4::new_vec4(0.0, 1.0, 0.0) };
+    let u = helper.cross(&normal).normalize();
+    let v = normal.cross(&u);
+    let projected: Vec<Vec2> = points.iter()
+        .map(|p| {
+            let d = p.sub(&points[0]);
+            Vec2::new(d.dot(&u), d.dot(&v))
+        })
+        .collect();
+
+    let turn = |a: usize, b: usize, c: usize| {
+        let (a, b, c) = (projected[a], projected[b], projected[c]);
+        (b.x() - a.x()) * (c.y() - a.y()) - (b.y() - a.y()) * (c.x() - a.x())
+    };
+
+    if (0..n).all(|i| turn((i+n-1)%n, i, (i+1)%n) > eps) {
+        return Ok((2..n).map(|i| [0, i-1, i]).collect());
+    }
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let corner = |i: usize| (remaining[(i+m-1)%m], remaining[i], remaining[(i+1)%m]);
+
+        let ear = (0..m).find(|&i| {
+            let (a, b, c) = corner(i);
+            turn(a, b, c) > eps && remaining.iter().all(|&p| {
+                // other corners may touch the ear in a shared point but not lie inside it
+                [a, b, c].contains(&p)
+                    || projected[p] == projected[a] || projected[p] == projected[c]
+                    || turn(a, b, p) < 0.0 || turn(b, c, p) < 0.0 || turn(c, a, p) < 0.0
+            })
+        });
+
+        match ear {
+            Some(i) => {
+                let (a, b, c) = corner(i);
+                triangles.push([a, b, c]);
+                remaining.remove(i);
+            }
+            None => {
+                let straight = (0..m).find(|&i| {
+                    let (a, b, c) = corner(i);
+                    turn(a, b, c).abs() <= eps
+                }).ok_or(())?;
+                remaining.remove(straight);
+            }
+        }
+    }
+
+    let (a, b, c) = (remaining[0], remaining[1], remaining[2]);
+    if turn(a, b, c) > eps {
+        triangles.push([a, b, c]);
+    }
+
+    Ok(triangles)
+}
+
+#[test]
+fn test_triangulate_polygon() {
+    let polygon = |coords: &[(f32, f32)]| -> Vec<Vec4> {
+        coords.iter().map(|&(x, y)| Vec4::new_point(x, y, 0.0)).collect()
+    };
+    let area = |points: &[Vec4], triangles: &[[usize; 3]]| -> f32 {
+        triangles.iter().map(|[a, b, c]| {
+            let (a, b, c) = (points[*a], points[*b], points[*c]);
+            b.sub(&a).cross(&c.sub(&a)).z() / 2.0
+        }).sum()
+    };
+
+    // an arrow head: fanning from the first corner would cover area outside of it
+    let arrow = polygon(&[(0.0, 0.0), (2.0, 1.0), (4.0, 0.0), (2.0, 3.0)]);
+    let triangles = triangulate_polygon(&arrow).unwrap();
+    assert_eq!(triangles.len(), 2);
+    assert!((area(&arrow, &triangles) - 4.0).abs() < 1e-5);
+
+    // an L shape, every triangle keeps the winding
+    let l_shape = polygon(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0)]);
+    let triangles = triangulate_polygon(&l_shape).unwrap();
+    assert_eq!(triangles.len(), 4);
+    assert!((area(&l_shape, &triangles) - 4.0).abs() < 1e-5);
+    assert!(triangles.iter().all(|t| area(&l_shape, &[*t]) > 0.0));
+
+    // a square with a corner halfway an edge needs no sliver triangle
+    let square = polygon(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+    let triangles = triangulate_polygon(&square).unwrap();
+    assert!((area(&square, &triangles) - 4.0).abs() < 1e-5);
+    assert!(triangles.iter().all(|t| area(&square, &[*t]) > 1e-3));
+
+    // a bow tie has no area, a line no corners to cut
+    assert!(triangulate_polygon(&polygon(&[(0.0, 0.0), (1.0, 1.0), (1.0, 0.0), (0.0, 1.0)])).is_err());
+    assert!(triangulate_polygon(&polygon(&[(0.0, 0.0), (1.0, 0.0)])).is_err());
+}
@@ -0,0 +1,83 @@
+use std::f32::consts::PI;
+use crate::figure::Mesh;
+use crate::vec2::Vec2;
+use crate::vec4::Vec4;
+
+/// How texture coordinates are projected onto a mesh. All projections are fitted to the box around
+/// the mesh, so the coordinates run from 0 to 1 over the whole mesh.
+#[derive(Clone, Copy, Debug)]
+pub enum UvProjection {
+    Planar(Vec4), // straight along the given direction onto the plane perpendicular to it
+    Spherical, // longitude and latitude around the center, v from the bottom pole to the top one
+    Cylindrical, // the angle around the z axis through the center and the height
+    Box, // planar along x, y or z, whichever the vertex normal points along the most
+}
+
+/// Projected coordinates of every vertex. Spherical and cylindrical coordinates wrap from 1 back to 0
+/// behind the center on the negative x side, faces across that seam stretch over the whole texture
+/// unless their vertices are split there.
+pub fn project(mesh: &Mesh, projection: UvProjection) -> Vec<Vec2> {
+    let Some(bounds) = mesh.aabb() else { return Vec::new() };
+    let center = bounds.center();
+    let size = bounds.size();
+    // coordinates along an axis of the box, from 0 at its minimum to 1 at its maximum
+    let along = |p: &Vec4, axis: usize| {
+        let extent = [size.x(), size.y(), size.z()][axis];
+        if extent > 0.0 { ([p.x(), p.y(), p.z()][axis] - [bounds.min.x(), bounds.min.y(), bounds.min.z()][axis]) / extent } else { 0.5 }
+    };
+    let longitude = |p: &Vec4| {
+        let from_center = p.sub(&center);
+        from_center.y().atan2(from_center.x()) / (2.0 * PI) + 0.5
+    };
+
+    match projection {
+        UvProjection::Planar(direction) => planar(&mesh.vertices, &direction),
+        UvProjection::Spherical => mesh.vertices.iter().map(|p| {
+            let from_center = p.sub(&center);
+            let length = from_center.length();
+            let latitude = if length > 0.0 { (from_center.z() / length).clamp(-1.0, 1.0).acos() / PI } else { 0.5 };
+            Vec2::new(longitude(p), 1.0 - latitude)
+        }).collect(),
+        UvProjection::Cylindrical => mesh.vertices.iter().map(|p| Vec2::new(longitude(p), along(p, 2))).collect(),
+        UvProjection::Box => {
+            let normals = if mesh.attributes.normals.len() == mesh.vertices.len() { mesh.attributes.normals.clone() } else { vertex_normals(mesh) };
+            mesh.vertices.iter().zip(normals.iter()).map(|(p, n)| {
+                let (x, y, z) = (n.x().abs(), n.y().abs(), n.z().abs());
+                if z >= x && z >= y { Vec2::new(along(p, 0), along(p, 1)) }
+                else if x >= y { Vec2::new(along(p, 1), along(p, 2)) }
+                else { Vec2::new(along(p, 0), along(p, 2)) }
+            }).collect()
+        }
+    }
+}
+
+/// The points projected on the plane perpendicular to the direction, fitted to the square from 0 to 1.
+/// u runs along the axis in the plane closest to x, or to y when looking along x.
+fn planar(points: &[Vec4], direction: &Vec4) -> Vec<Vec2> {
+    let normal = Vec4::new_vec4(direction.x(), direction.y(), direction.z()).normalize();
+    let helper = if normal.x().abs() < 0.9 { Vec4::new_vec4(1.0, 0.0, 0.0) } else { Vec4::new_vec4(0.0, 1.0, 0.0) };
+    let u_axis = helper.sub(&normal.scale(helper.dot(&normal))).normalize();
+    let v_axis = normal.cross(&u_axis);
+
+    let projected: Vec<(f32, f32)> = points.iter().map(|p| (p.dot(&u_axis), p.dot(&v_axis))).collect();
+    let fit = |values: Vec<f32>| {
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        values.into_iter().map(move |value| if max > min { (value - min) / (max - min) } else { 0.5 })
+    };
+    let us = fit(projected.iter().map(|&(u, _)| u).collect());
+    let vs = fit(projected.iter().map(|&(_, v)| v).collect());
+    us.zip(vs).map(|(u, v)| Vec2::new(u, v)).collect()
+}
+
+/// The normals of the faces around every vertex added up, weighted by their area
+fn vertex_normals(mesh: &Mesh) -> Vec<Vec4> {
+    let mut normals = vec![Vec4::new_vec4(0.0, 0.0, 0.0); mesh.vertices.len()];
+    for face in mesh.faces.iter() {
+        let face_normal = mesh.face_normal(face);
+        for &i in face.indexes.iter() {
+            normals[i] = normals[i].add(&face_normal);
+        }
+    }
+    normals
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::figure::{Face, Mesh};
+
+/// How many faces run along every edge, keyed by its two vertices
+type EdgeCounts = HashMap<(usize, usize), u32>;
+
+/// Everything `validate` found wrong with a mesh. Faces and vertices are given by their index.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub out_of_range_faces: Vec<usize>, // faces pointing to vertices that don't exist
+    pub degenerate_faces: Vec<usize>, // less than three different corners, or no area
+    pub non_manifold_edges: Vec<(usize, usize)>, // edges of more than two faces
+    pub inconsistent_edges: Vec<(usize, usize)>, // edges two faces run along in the same direction
+    pub isolated_vertices: Vec<usize>,
+    pub holes: Vec<Vec<usize>>, // the vertices around every hole
+    pub euler_characteristic: i64, // vertices - edges + faces, 2 for a closed surface without handles
+}
+
+impl ValidationReport {
+    /// A mesh without any problems is a closed, consistently wound surface
+    pub fn is_valid(&self) -> bool {
+        self.problems().is_empty()
+    }
+
+    /// Every problem as a line of text
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.out_of_range_faces.is_empty() {
+            problems.push(format!("{} faces point to vertices that don't exist: {:?}", self.out_of_range_faces.len(), self.out_of_range_faces));
+        }
+        if !self.degenerate_faces.is_empty() {
+            problems.push(format!("{} faces have no area", self.degenerate_faces.len()));
+        }
+        if !self.non_manifold_edges.is_empty() {
+            problems.push(format!("{} edges belong to more than two faces", self.non_manifold_edges.len()));
+        }
+        if !self.inconsistent_edges.is_empty() {
+            problems.push(format!("{} edges are shared by faces that disagree about their winding", self.inconsistent_edges.len()));
+        }
+        if !self.isolated_vertices.is_empty() {
+            problems.push(format!("{} vertices aren't part of any face", self.isolated_vertices.len()));
+        }
+        if !self.holes.is_empty() {
+            let sizes: Vec<usize> = self.holes.iter().map(|hole| hole.len()).collect();
+            problems.push(format!("{} holes with {:?} edges", self.holes.len(), sizes));
+        }
+        problems
+    }
+}
+
+pub fn validate(mesh: &Mesh) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let vertices_amt = mesh.vertices.len();
+
+    let mut used = vec![false; vertices_amt];
+    for face in mesh.faces.iter() {
+        for &i in face.indexes.iter().filter(|&&i| i < vertices_amt) { used[i] = true; }
+    }
+    let mut directed_edges: EdgeCounts = HashMap::new();
+    let mut faces_amt = 0;
+    for (face_i, face) in mesh.faces.iter().enumerate() {
+        if face.indexes.iter().any(|&i| i >= vertices_amt) {
+            report.out_of_range_faces.push(face_i);
+            continue;
+        }
+        let distinct: HashSet<usize> = face.indexes.iter().copied().collect();
+        if distinct.len() < 3 || mesh.face_normal(face).length() <= 0.0 {
+            // left out of the edges, they would only hide the real connections
+            report.degenerate_faces.push(face_i);
+            continue;
+        }
+        faces_amt += 1;
+        for (a, b) in edges(face) {
+            *directed_edges.entry((a, b)).or_default() += 1;
+        }
+    }
+
+    let undirected = undirected_edges(&directed_edges);
+    for (&(a, b), &amt) in undirected.iter() {
+        if amt > 2 {
+            report.non_manifold_edges.push((a, b));
+        }
+        else if amt == 2 && (directed_edges.get(&(a, b)) != Some(&1) || directed_edges.get(&(b, a)) != Some(&1)) {
+            report.inconsistent_edges.push((a, b));
+        }
+    }
+    report.non_manifold_edges.sort();
+    report.inconsistent_edges.sort();
+
+    report.isolated_vertices = (0..vertices_amt).filter(|&v| !used[v]).collect();
+    report.holes = boundary_loops(&directed_edges, &undirected);
+
+    let used_amt = directed_edges.keys().map(|&(a, _)| a).collect::<HashSet<usize>>().len() as i64;
+    report.euler_characteristic = used_amt - undirected.len() as i64 + faces_amt as i64;
+    report
+}
+
+/// Drops faces with missing vertices or without area, joins corners repeated in a row, fills holes of
+/// at most `max_hole_edges` edges, and turns faces so every connected part is wound consistently, outward for closed parts
+pub fn repair(mesh: &mut Mesh, max_hole_edges: usize) {
+    let vertices_amt = mesh.vertices.len();
+    let faces = std::mem::take(&mut mesh.faces);
+    mesh.faces = faces.into_iter()
+        .filter(|face| face.indexes.iter().all(|&i| i < vertices_amt))
+        // corners that follow each other on the same vertex are joined, like `weld` does
+        .map(|mut face| {
+            face.indexes.dedup();
+            while face.indexes.len() > 1 && face.indexes.first() == face.indexes.last() {
+                face.indexes.pop();
+            }
+            face
+        })
+        .filter(|face| face.indexes.iter().collect::<HashSet<_>>().len() >= 3)
+        .collect();
+    let has_area: Vec<bool> = mesh.faces.iter().map(|face| mesh.face_normal(face).length() > 0.0).collect();
+    let mut has_area = has_area.into_iter();
+    mesh.faces.retain(|_| has_area.next().unwrap());
+
+    orient_consistently(mesh);
+
+    let (directed_edges, undirected) = count_edges(mesh);
+    for hole in boundary_loops(&directed_edges, &undirected) {
+        if hole.len() <= max_hole_edges {
+            mesh.faces.push(Face::new(hole));
+        }
+    }
+
+    // closed parts that enclose a negative volume are inside out
+    let (_, undirected) = count_edges(mesh);
+    for component in components(mesh) {
+        let closed = component.iter()
+            .all(|&face_i| edges(&mesh.faces[face_i]).all(|(a, b)| undirected.get(&(a.min(b), a.max(b))) == Some(&2)));
+        if !closed { continue; }
+        let volume: f32 = component.iter().map(|&face_i| {
+            let indexes = &mesh.faces[face_i].indexes;
+            let a = mesh.vertices[indexes[0]];
+            (2..indexes.len()).map(|i| a.dot(&mesh.vertices[indexes[i-1]].cross(&mesh.vertices[indexes[i]]))).sum::<f32>()
+        }).sum();
+        if volume < 0.0 {
+            for &face_i in component.iter() {
+                mesh.faces[face_i].indexes.reverse();
+            }
+        }
+    }
+}
+
+fn count_edges(mesh: &Mesh) -> (EdgeCounts, EdgeCounts) {
+    let mut directed_edges: EdgeCounts = HashMap::new();
+    for face in mesh.faces.iter() {
+        for edge in edges(face) {
+            *directed_edges.entry(edge).or_default() += 1;
+        }
+    }
+    let undirected = undirected_edges(&directed_edges);
+    (directed_edges, undirected)
+}
+
+/// How many faces every edge belongs to, edges from a vertex to itself left out
+fn undirected_edges(directed_edges: &EdgeCounts) -> EdgeCounts {
+    let mut undirected: EdgeCounts = HashMap::new();
+    for (&(a, b), &amt) in directed_edges.iter() {
+        if a != b {
+            *undirected.entry((a.min(b), a.max(b))).or_default() += amt;
+        }
+    }
+    undirected
+}
+
+fn edges(face: &Face) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let n = face.indexes.len();
+    (0..n).map(move |i| (face.indexes[i], face.indexes[(i+1)%n]))
+}
+
+/// Chains the edges with a single face into loops, running the other way than the faces along them,
+/// so a loop is wound like a face that would close the hole
+fn boundary_loops(directed_edges: &EdgeCounts, undirected: &EdgeCounts) -> Vec<Vec<usize>> {
+    let mut next: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut boundary: Vec<(usize, usize)> = directed_edges.keys()
+        .filter(|&&(a, b)| a != b && undirected[&(a.min(b), a.max(b))] == 1)
+        .map(|&(a, b)| (b, a))
+        .collect();
+    boundary.sort();
+    for &(from, to) in boundary.iter() {
+        next.entry(from).or_default().push(to);
+    }
+
+    let mut loops = Vec::new();
+    for &(start, _) in boundary.iter() {
+        let mut hole = Vec::new();
+        let mut current = start;
+        while let Some(to) = next.get_mut(&current).and_then(|targets| targets.pop()) {
+            hole.push(current);
+            current = to;
+            if current == start { break; }
+        }
+        if !hole.is_empty() { loops.push(hole); }
+    }
+    loops
+}
+
+/// The faces grouped by the parts of the surface they are connected through edges in
+fn components(mesh: &Mesh) -> Vec<Vec<usize>> {
+    let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (face_i, face) in mesh.faces.iter().enumerate() {
+        for (a, b) in edges(face) {
+            edge_faces.entry((a.min(b), a.max(b))).or_default().push(face_i);
+        }
+    }
+
+    let mut component_of = vec![usize::MAX; mesh.faces.len()];
+    let mut components = Vec::new();
+    for start in 0..mesh.faces.len() {
+        if component_of[start] != usize::MAX { continue; }
+        let mut component = vec![start];
+        component_of[start] = components.len();
+        let mut queue = VecDeque::from([start]);
+        while let Some(face_i) = queue.pop_front() {
+            for (a, b) in edges(&mesh.faces[face_i]) {
+                for &neighbour in edge_faces[&(a.min(b), a.max(b))].iter() {
+                    if component_of[neighbour] == usize::MAX {
+                        component_of[neighbour] = components.len();
+                        component.push(neighbour);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// Turns faces so neighbours across an edge of two faces run along it in opposite directions,
+/// spreading out from the first face of every part
+fn orient_consistently(mesh: &mut Mesh) {
+    let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (face_i, face) in mesh.faces.iter().enumerate() {
+        for (a, b) in edges(face) {
+            edge_faces.entry((a.min(b), a.max(b))).or_default().push(face_i);
+        }
+    }
+
+    let mut visited = vec![false; mesh.faces.len()];
+    for start in 0..mesh.faces.len() {
+        if visited[start] { continue; }
+        visited[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(face_i) = queue.pop_front() {
+            let face_edges: Vec<(usize, usize)> = edges(&mesh.faces[face_i]).collect();
+            for (a, b) in face_edges {
+                let neighbours = &edge_faces[&(a.min(b), a.max(b))];
+                if neighbours.len() != 2 { continue; }
+                let neighbour = if neighbours[0] == face_i { neighbours[1] } else { neighbours[0] };
+                if visited[neighbour] { continue; }
+                visited[neighbour] = true;
+                if edges(&mesh.faces[neighbour]).any(|e| e == (a, b)) {
+                    mesh.faces[neighbour].indexes.reverse();
+                }
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
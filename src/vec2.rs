@@ -1,3 +1,4 @@
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec2 {
     elems: [f32;2]
 }
@@ -9,4 +10,4 @@ impl Vec2 {
 
     pub fn x(&self) -> f32 { return self.elems[0] }
     pub fn y(&self) -> f32 { return self.elems[1] }
-}
\ No newline at end of file
+}
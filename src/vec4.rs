@@ -47,6 +47,54 @@ impl Vec4 {
         }
         Self{elems}
     }
+
+    /// Component-wise sum, a point plus a vector stays a point.
+    pub fn add(&self, other: &Self) -> Self {
+        let mut elems = self.elems;
+        for (el, other_el) in elems.iter_mut().zip(other.elems.iter()) {
+            *el += other_el;
+        }
+        Self{elems}
+    }
+
+    /// Component-wise difference, the difference of two points is a vector.
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg_homogeneous())
+    }
+
+    /// Scales the x, y and z components, leaving the homogeneous coordinate untouched.
+    pub fn scale(&self, factor: f32) -> Self {
+        let mut elems = self.elems;
+        for el in elems.iter_mut().take(3) {
+            *el *= factor;
+        }
+        Self{elems}
+    }
+
+    /// Dot product of the x, y and z components.
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.x()*other.x() + self.y()*other.y() + self.z()*other.z()
+    }
+
+    pub fn cross(&self, other: &Self) -> Self {
+        Self::new_vec4(
+            self.y()*other.z() - self.z()*other.y(),
+            self.z()*other.x() - self.x()*other.z(),
+            self.x()*other.y() - self.y()*other.x(),
+        )
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    fn neg_homogeneous(&self) -> Self {
+        let mut elems = self.elems;
+        for el in elems.iter_mut() {
+            *el = -*el;
+        }
+        Self{elems}
+    }
 }
 
 fn dot_product(u: &[f32;4], v: &[f32;4]) -> f32 {